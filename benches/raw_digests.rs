@@ -0,0 +1,95 @@
+// Compares the hex String pipeline of npg3 with the raw digests of npg4,
+// and with npg5, which stretches one key and reads the rest from SHAKE256.
+// Run it with `cargo bench --bench raw_digests`.
+//
+// Every version hash the same seeds with the same settings. The allocator
+// below counts every allocation, so the report shows how much memory each
+// password cost as well as how long it took.
+
+use neo_passgen::algorithm::Algorithm;
+use neo_passgen::hashing_arguments::HashArguments;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// Passes every call to the system allocator and counts it.
+struct Counting;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES: AtomicU64 = AtomicU64::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// What one version cost over every password.
+struct Measurement {
+    elapsed: Duration,
+    allocations: u64,
+    bytes: u64
+}
+
+// Makes `passwords` passwords of `length` characters with one algorithm.
+fn measure(algorithm: Algorithm, hashcount: u64, length: u64, passwords: u64) -> Measurement {
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    for a in 0..passwords {
+        let mut args = HashArguments::new(format!("benchmark seed {}", a), hashcount, length, 1).unwrap();
+        args.set_algorithm(algorithm);
+
+        std::hint::black_box(args.run().unwrap());
+    }
+
+    Measurement {
+        elapsed: start.elapsed(),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        bytes: BYTES.load(Ordering::Relaxed) - bytes
+    }
+}
+
+fn main() {
+
+    let passwords = 200;
+
+    println!("{:>6} {:>6} {:>5} {:>12} {:>14} {:>14}",
+        "hashes", "length", "algo", "passwords/s", "allocs/pass", "bytes/pass");
+
+    for (hashcount, length) in [(1, 32), (1, 1024), (1000, 32), (1000, 256)] {
+        for algorithm in [Algorithm::V3, Algorithm::V4, Algorithm::V5] {
+
+            // Warms up the caches before timing.
+            measure(algorithm, hashcount, length, 10);
+
+            let result = measure(algorithm, hashcount, length, passwords);
+
+            println!("{:>6} {:>6} {:>5} {:>12.0} {:>14} {:>14}",
+                hashcount,
+                length,
+                algorithm,
+                passwords as f64 / result.elapsed.as_secs_f64(),
+                result.allocations / passwords,
+                result.bytes / passwords);
+        }
+    }
+}
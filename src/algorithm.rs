@@ -0,0 +1,925 @@
+// The versioned hashing schemes. Once a version is released its output is
+// frozen, so passwords made with it can always be regenerated.
+
+use crate::error::PassgenError;
+
+// The stretching functions used by the newer algorithms.
+use crate::kdf::Kdf;
+
+// The unbiased encoder used by the newer algorithms.
+use crate::alphabet::{Alphabet, ByteStream};
+
+// The password rules the newer algorithms can follow.
+use crate::policy::Policy;
+
+// Passphrases are made of words instead of characters.
+use crate::passphrase::{self, Passphrase};
+
+// Shapes like "Cvcvnocvcv".
+use crate::template::Template;
+
+// Numeric PINs.
+use crate::pin::{self, Pin};
+
+// SHA512 hashes binary data
+use sha2::{Sha512, Digest};
+
+// Base94 converts data to base94 String
+use base94::encode;
+
+// Wipes the intermediate hashes once they are dropped.
+use zeroize::Zeroizing;
+
+// Holds the decoded key, locked in memory if the program asks for it.
+use crate::secret::KeyBuffer;
+
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+// What the key is turned into. Only one of these can be made at once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Encoder {
+
+    // A password of characters from the alphabet.
+    Alphabet(Alphabet),
+
+    // A password of characters from the alphabet that follows the policy.
+    Policy(Policy, Alphabet),
+
+    // A passphrase of `length` words.
+    Passphrase(Passphrase),
+
+    // A password in the shape of a template. The length must be the template's.
+    Template(Template),
+
+    // A PIN of `length` digits.
+    Pin(Pin),
+}
+
+impl Encoder {
+
+    // Makes passwords from the alphabet, keeping the policy if there is one.
+    pub fn with_alphabet(self, alphabet: Alphabet) -> Self {
+        match self {
+            Self::Policy(policy, _) => Self::Policy(policy, alphabet),
+            _ => Self::Alphabet(alphabet),
+        }
+    }
+
+    // Makes passwords that follow the policy, keeping the alphabet if there is one.
+    pub fn with_policy(self, policy: Policy) -> Self {
+        match self {
+            Self::Alphabet(alphabet) | Self::Policy(_, alphabet) => Self::Policy(policy, alphabet),
+            _ => Self::Policy(policy, Alphabet::default()),
+        }
+    }
+
+    // The characters a password is made of, or None for the other modes.
+    pub fn alphabet(&self) -> Option<&Alphabet> {
+        match self {
+            Self::Alphabet(alphabet) | Self::Policy(_, alphabet) => Some(alphabet),
+            _ => None,
+        }
+    }
+
+    // The policy a password follows, if any.
+    pub fn policy(&self) -> Option<&Policy> {
+        match self {
+            Self::Policy(policy, _) => Some(policy),
+            _ => None,
+        }
+    }
+
+    // How a passphrase is put together, if this makes one.
+    pub fn passphrase(&self) -> Option<&Passphrase> {
+        match self {
+            Self::Passphrase(passphrase) => Some(passphrase),
+            _ => None,
+        }
+    }
+
+    // The template a password is filled into, if any.
+    pub fn template(&self) -> Option<&Template> {
+        match self {
+            Self::Template(template) => Some(template),
+            _ => None,
+        }
+    }
+
+    // How a PIN is made, if this makes one.
+    pub fn pin(&self) -> Option<&Pin> {
+        match self {
+            Self::Pin(pin) => Some(pin),
+            _ => None,
+        }
+    }
+
+    // An error of the mode's own kind.
+    fn error(&self, reason: String) -> PassgenError {
+        match self {
+            Self::Alphabet(_) => PassgenError::InvalidAlphabet(reason),
+            Self::Policy(..) => PassgenError::InvalidPolicy(reason),
+            Self::Passphrase(_) => PassgenError::InvalidPassphrase(reason),
+            Self::Template(_) => PassgenError::InvalidTemplate(reason),
+            Self::Pin(_) => PassgenError::InvalidPin(reason),
+        }
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::Alphabet(Alphabet::default())
+    }
+}
+
+// Every hashing scheme the library can run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Algorithm {
+
+    // The original hash_base94() scheme. Each 75 character chunk hashes the
+    // seed, the chunk index, the length and every count from 0 to
+    // `hashcount`, then base94 encodes the raw digest.
+    V0,
+
+    // Legacy scheme. Feeds the same chunk into one hasher `hashcount` times,
+    // which is just hashing a longer message. Kept to regenerate old passwords.
+    V1,
+
+    // Stretched scheme. Each chunk goes through a Kdf, iterated SHA-512 by
+    // default, where `hashcount` rounds really cost `hashcount` hashes.
+    V2,
+
+    // Hashes like V2, then maps the raw digest bytes onto any Alphabet
+    // without bias instead of base94 encoding the hex String.
+    V3,
+
+    // Encodes like V3, but never turns the digests into hex: each 64 byte
+    // chunk is kept as raw bytes from the Kdf to the Alphabet. The chunk
+    // input is binary too, with the seed's length in front so no two
+    // seeds, lengths and indexes can run together into the same input.
+    V4,
+
+    // Has no chunks: the seed is stretched once, with the same input as
+    // V4's chunk 0, and the whole password is read from SHAKE256 of that
+    // key. Any length costs one run of the Kdf. The only version that can
+    // make prefix passwords.
+    V5,
+}
+
+// Passwords each frozen version must always produce, as
+// (algorithm, seed, hashcount, length, password) with the default kdf and alphabet.
+// A version whose answers change is no longer the same version.
+pub const KNOWN_ANSWERS: &[(Algorithm, &str, u64, u64, &str)] = &[
+    (Algorithm::V0, "apple", 1, 32, r#"<#*6'Y:[tndK3%T`qtD$(C`eIS])]A6?"#),
+    (Algorithm::V0, "banana", 6, 32, r#"Q1!0.cxUpSJcc@m4y-PEX~O=nRZ0_5{3"#),
+    (Algorithm::V0, "foo", 8, 55, r#"4i`M~Mf9r7Tk`]N;q6t'lpuN(/~qFC?V9u5&=tMO}4#m!$gBcBZqr<a"#),
+    (Algorithm::V1, "apple", 1, 32, r#"d\H8lE\REmqP-kiP0*jzrQpXm3TtX}KS"#),
+    (Algorithm::V1, "hello", 100, 50, r#"W|6_GfA9k<-KHO*BabbnGu_,7`"$o4&Zg>*.J2<lj~=Dct+!iN"#),
+    (Algorithm::V1, "peanut butter", 5000, 80,
+        r#"yU+4rrd]E+Q#(wlN9:~qG}OcQ_r>\<U7??*QtIPGS56(gV>fF!Z'lZU}tyXMZfzW2q>S/kpvZ/&Y_pb/"#),
+    (Algorithm::V2, "apple", 1, 32, r#"d\H8lE\REmqP-kiP0*jzrQpXm3TtX}KS"#),
+    (Algorithm::V2, "hello", 100, 50, r#"rQD8nd5Ye2kw4zY<LK$?rcv*[c[_ua%xJ}pO^'SJ0e.2}KLqTw"#),
+    (Algorithm::V2, "peanut butter", 5000, 80,
+        r#"snTykwrAWxu<y{f,X7""IJ|e*#?lp~P$Z07"z%PnD7S)y7L`z).a;-.h;RqdyaxVp]AWS4m;TIv#%fIW"#),
+    (Algorithm::V3, "apple", 1, 32, r#"q0w@Qg<]U14aAt%|FMNV~wG<Y)Y,Y]e!"#),
+    (Algorithm::V3, "hello", 100, 50, r#"wH!N:7,kO1c*'Z.kg&v9[DBOTU|mnusmRbBC$|s'k@2<:J/Vs#"#),
+    (Algorithm::V3, "peanut butter", 5000, 80,
+        r#"i.o>P{^T]7Gg,HU^n%K\{=_F%0:qy0!t<u7w5mZ8}j/gq!>3P=0X'.l&-|{5SYy_@Q.7'6]z>F<QCgV6"#),
+    (Algorithm::V4, "apple", 1, 32, r#"B[c^e){RgS)%XPs+MFvZiynP'w~v:N-#"#),
+    (Algorithm::V4, "hello", 100, 50, r#"+NCz_36EY)/)>L!'"JaU;_2#)d5Pl0aWA(C#1-l=D,]T*dz~NB"#),
+    (Algorithm::V4, "peanut butter", 5000, 80,
+        r#""8*94gBkUs8c~>jwf9jwvhz[-ibZQj2]MGfcJWUl=.Ty*NIm7l*}a$/5@Bo+Dq^]`Y[Bz0H}Nk4{9d3^"#),
+    (Algorithm::V5, "apple", 1, 32, r#"0O6BZeF5$`9b}?pQM3Oh!_#Hr?G-]"5]"#),
+    (Algorithm::V5, "hello", 100, 50, r#"~*j0[cwJSLv1U2%`]8B;|O1Gxe=_kC>SvrAC;88VlV35Lrt!b="#),
+    (Algorithm::V5, "peanut butter", 5000, 80,
+        r#"H'^DL3"yBy2?foNzmK0/*-o"gmmh~61S*swInK<e%~6W!evO;dgS1<ICS"lMJw9o}*?])-wOxm!fN.rN"#),
+];
+
+// The words the known passphrases are drawn from.
+const KNOWN_WORDS: &str = "alpha\nbravo\ncharlie\ndelta\necho\nfoxtrot\ngolf\nhotel";
+
+// Passphrases, templates and PINs each version that makes them must always
+// produce, as (algorithm, mode, seed, hashcount, length, output). The mode
+// is "words" for KNOWN_WORDS with capitals and a digit, "pin" for a PIN
+// that skips the common ones, or else a template.
+pub const KNOWN_ENCODINGS: &[(Algorithm, &str, &str, u64, u64, &str)] = &[
+    (Algorithm::V4, "words", "apple", 10, 6, "Echo-Charlie-Golf6-Delta-Hotel-Alpha"),
+    (Algorithm::V4, "long", "apple", 10, 14, "GomuMasyGive8^"),
+    (Algorithm::V4, "Cvcvnocvcv", "hello", 100, 10, "Xeha6#hohi"),
+    (Algorithm::V4, "pin", "apple", 10, 6, "353827"),
+    (Algorithm::V5, "words", "apple", 10, 6, "Echo-Alpha-Delta3-Golf-Foxtrot-Foxtrot"),
+    (Algorithm::V5, "long", "apple", 10, 14, "Rolb3]QuduRaxi"),
+    (Algorithm::V5, "Cvcvnocvcv", "hello", 100, 10, "Jaso7-sofi"),
+    (Algorithm::V5, "pin", "apple", 10, 6, "123390"),
+];
+
+// The encoder a mode of KNOWN_ENCODINGS stands for.
+fn known_encoder(mode: &str) -> Result<Encoder, PassgenError> {
+    match mode {
+        "words" => {
+            let mut passphrase = Passphrase::new(crate::wordlist::Wordlist::parse(KNOWN_WORDS)?);
+            passphrase.set_capitalize(true);
+            passphrase.set_digit(true);
+            Ok(Encoder::Passphrase(passphrase))
+        }
+        "pin" => {
+            let mut pin = Pin::new();
+            pin.set_skip_common(true);
+            Ok(Encoder::Pin(pin))
+        }
+        _ => Ok(Encoder::Template(Template::from_id(mode)?)),
+    }
+}
+
+// Runs every known answer, single and multithreaded, and returns
+// each algorithm that gave a different password.
+pub fn self_test() -> Result<(), Vec<Algorithm>> {
+
+    use crate::hashing_arguments::HashArguments;
+
+    let mut failed = Vec::new();
+
+    // Passwords use the default encoder.
+    let answers = KNOWN_ANSWERS
+        .iter()
+        .map(|&(algorithm, seed, hashcount, length, password)|
+            (algorithm, Ok(Encoder::default()), seed, hashcount, length, password))
+        .chain(KNOWN_ENCODINGS
+            .iter()
+            .map(|&(algorithm, mode, seed, hashcount, length, output)|
+                (algorithm, known_encoder(mode), seed, hashcount, length, output)));
+
+    for (algorithm, encoder, seed, hashcount, length, password) in answers {
+        for threads in [1, 3] {
+
+            let output = encoder.clone()
+                .and_then(|encoder| HashArguments::new(String::from(seed), hashcount, length, threads)
+                    .map(|args| (args, encoder)))
+                .and_then(|(mut args, encoder)| {
+                    args.set_algorithm(algorithm);
+                    args.set_encoder(encoder);
+
+                    if threads == 1 {
+                        args.hash()?;
+                    } else {
+                        args.hash_multi()?;
+                    }
+
+                    args.encode()?;
+                    Ok(args.finish())
+                });
+
+            if output.as_deref() != Ok(password) && !failed.contains(&algorithm) {
+                failed.push(algorithm);
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(failed)
+    }
+}
+
+impl Algorithm {
+
+    // Every version, oldest first.
+    pub const ALL: [Algorithm; 6] = [
+        Algorithm::V0, Algorithm::V1, Algorithm::V2, Algorithm::V3, Algorithm::V4, Algorithm::V5];
+
+    // The newest algorithm.
+    pub const LATEST: Algorithm = Algorithm::V5;
+
+    // The algorithm used unless another one is asked for, always the newest.
+    // A new version moves it and changes every password made without one,
+    // so those passwords are regenerated from their parameter strings.
+    pub const DEFAULT: Algorithm = Self::LATEST;
+
+    // Whether the version stretches its chunks with a Kdf.
+    pub fn uses_kdf(&self) -> bool {
+        *self >= Self::V2
+    }
+
+    // The short identifier used on the command line.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::V0 => "npg0",
+            Self::V1 => "npg1",
+            Self::V2 => "npg2",
+            Self::V3 => "npg3",
+            Self::V4 => "npg4",
+            Self::V5 => "npg5",
+        }
+    }
+
+    // How many password characters one chunk of the hash covers.
+    pub(crate) fn chunk_size(&self) -> u64 {
+        match self {
+            Self::V0 => 75,
+            Self::V1 | Self::V2 | Self::V3 => 35,
+
+            // One digest byte for every character, before rejections.
+            Self::V4 => 64,
+
+            // One key covers the whole password.
+            Self::V5 => u64::MAX,
+        }
+    }
+
+    // How many chunks a password of `length` characters hashes. V0 always
+    // hashed one more than the length needs, since a base94 chunk can come
+    // out shorter than 75 characters and the next one fills the rest.
+    pub(crate) fn chunk_count(&self, length: u64) -> u64 {
+        match self {
+            Self::V0 => length / self.chunk_size() + 1,
+            _ => length.div_ceil(self.chunk_size()),
+        }
+    }
+
+    // Hashes chunk `index` of the seed onto the end of `output`. V0 adds the
+    // finished base94 characters, V1 to V3 an uppercase hex String, and V4
+    // and V5 the raw digest. V0 and V1 predate the Kdf trait and ignore `kdf`.
+    pub(crate) fn hash_chunk(
+        &self,
+        kdf: &dyn Kdf,
+        seed: &str,
+        length: u64,
+        hashes: u64,
+        index: u64,
+        output: &mut Vec<u8>) -> Result<(), PassgenError> {
+
+        // Concatenates the length and the index. Two u64s have at most 40
+        // digits, so the String never grows and leaves a copy behind.
+        let seed_chunk = || {
+            let mut chunk = Zeroizing::new(String::with_capacity(seed.len() + 40));
+            chunk.push_str(seed);
+
+            // Writing to a String never fails.
+            let _ = write!(chunk, "{}{}", length, index);
+
+            chunk
+        };
+
+        match self {
+            Self::V0 => {
+
+                // Creates a hashing object.
+                let mut hasher = Sha512::new();
+
+                // Sets the seed, then makes it unique for the chunk and the length.
+                hasher.update(seed);
+                hasher.update(index.to_string());
+                hasher.update(length.to_string());
+
+                // Hashes the number of times as the count.
+                for count in 0..hashes {
+                    hasher.update(count.to_string());
+                }
+
+                // Encodes the final hash as a base94 String.
+                let mut carrier = Zeroizing::new(encode(&hasher.finalize(), 94));
+
+                // Sets the size of the String.
+                carrier.truncate(75);
+
+                output.extend_from_slice(carrier.as_bytes());
+            }
+            Self::V1 => {
+
+                // Creates hasher object.
+                let mut hasher = Sha512::new();
+
+                // Hashes the input multiple times
+                let seed_chunk = seed_chunk();
+                for _i in 0..hashes {
+                    hasher.update(seed_chunk.as_bytes());
+                }
+
+                // Writes the finished seed
+                push_hex(output, &hasher.finalize());
+            }
+            Self::V2 | Self::V3 => {
+
+                // Stretches the chunk.
+                let key = Zeroizing::new(kdf.derive(seed_chunk().as_bytes(), hashes)?);
+
+                // Writes the finished seed
+                push_hex(output, key.as_ref());
+            }
+            Self::V4 | Self::V5 => {
+
+                // Writes the seed with its length in front, then the length and index.
+                let mut input = Zeroizing::new(Vec::with_capacity(seed.len() + 24));
+                input.extend_from_slice(&(seed.len() as u64).to_be_bytes());
+                input.extend_from_slice(seed.as_bytes());
+                input.extend_from_slice(&length.to_be_bytes());
+                input.extend_from_slice(&index.to_be_bytes());
+
+                // Stretches the chunk and keeps the digest as it is.
+                let key = Zeroizing::new(kdf.derive(&input, hashes)?);
+                output.extend_from_slice(key.as_ref());
+            }
+        }
+
+        Ok(())
+    }
+
+    // Whether a shorter password can be a prefix of a longer one. Needs a
+    // key that doesn't depend on the length, read one byte after another.
+    pub fn supports_prefix(&self) -> bool {
+        *self >= Self::V5
+    }
+
+    // Whether the version keeps the raw digests, which passphrases,
+    // templates and PINs are drawn from.
+    pub fn supports_raw_digests(&self) -> bool {
+        *self >= Self::V4
+    }
+
+    // Checks that the algorithm can make what the encoder asks for, at
+    // this length and in prefix mode if it was asked for.
+    pub(crate) fn check_encoding(&self, encoder: &Encoder, prefix: bool, length: u64) -> Result<(), PassgenError> {
+
+        match encoder {
+            Encoder::Alphabet(alphabet) => {
+                if *self < Self::V3 && *alphabet != Alphabet::default() {
+                    return Err(encoder.error(format!("{} only supports the default alphabet", self)));
+                }
+            }
+            Encoder::Policy(..) => {
+                if *self < Self::V3 {
+                    return Err(encoder.error(format!("{} does not support policies", self)));
+                }
+            }
+            Encoder::Passphrase(_) | Encoder::Template(_) | Encoder::Pin(_) => {
+                if !self.supports_raw_digests() {
+                    return Err(encoder.error(format!("{} can only make passwords", self)));
+                }
+            }
+        }
+
+        match encoder {
+            Encoder::Template(template) if template.len() as u64 != length => {
+                return Err(encoder.error(
+                    format!("'{}' makes passwords of {} characters", template.id(), template.len())));
+            }
+            Encoder::Pin(_) if !(pin::MIN_LENGTH..=pin::MAX_LENGTH).contains(&length) => {
+                return Err(encoder.error(
+                    format!("a PIN has {} to {} digits", pin::MIN_LENGTH, pin::MAX_LENGTH)));
+            }
+            Encoder::Passphrase(_) if length > passphrase::MAX_WORDS => {
+                return Err(PassgenError::Overflow(
+                    format!("a passphrase of {} words (at most {})", length, passphrase::MAX_WORDS)));
+            }
+            Encoder::Passphrase(passphrase) => {
+
+                // The words have to be told apart, or two different picks
+                // could make the same passphrase.
+                let separator = passphrase.separator();
+
+                if let Some((word, longer)) = passphrase.wordlist().prefix_pair(separator) {
+                    if separator.is_empty() {
+                        return Err(encoder.error(format!(
+                            "'{}' is the start of '{}', so the words need a separator", word, longer)));
+                    }
+
+                    return Err(encoder.error(format!(
+                        "'{}' and the separator are the start of '{}', so they can't be told apart", word, longer)));
+                }
+            }
+            _ => (),
+        }
+
+        if prefix {
+            if !self.supports_prefix() {
+                return Err(PassgenError::UnsupportedPrefix(self.id().to_owned()));
+            }
+
+            // A policy shuffles the characters, so nothing stays in place,
+            // and the other modes don't make passwords one character at a time.
+            match encoder {
+                Encoder::Alphabet(_) => (),
+                Encoder::Policy(..) => return Err(encoder.error(String::from("a policy can't be used in prefix mode"))),
+                _ => return Err(encoder.error(String::from("prefix mode only makes passwords"))),
+            }
+        }
+
+        Ok(())
+    }
+
+    // Turns the hashed chunks into a password of `length` characters, or a
+    // passphrase of `length` words. V0 to V2 only know base94, so they
+    // refuse any other alphabet or a policy.
+    pub(crate) fn encode(
+        &self,
+        hashed: &[u8],
+        encoder: &Encoder,
+        prefix: bool,
+        length: usize) -> Result<String, PassgenError> {
+
+        self.check_encoding(encoder, prefix, length as u64)?;
+
+        match self {
+            Self::V0 => {
+
+                // The chunks are already encoded.
+                let mut password = std::str::from_utf8(hashed)
+                    .map_err(|_| PassgenError::EncodingFailure)?
+                    .to_owned();
+
+                // Catches a hash that is too short to fill the password.
+                if password.len() < length {
+                    return Err(PassgenError::EncodingFailure);
+                }
+
+                password.truncate(length);
+
+                Ok(password)
+            }
+            Self::V1 | Self::V2 => {
+
+                // Encodes the String.
+                let mut password = encode(hashed, 94);
+
+                // Catches a hash that is too short to fill the password.
+                if password.len() < length {
+                    return Err(PassgenError::EncodingFailure);
+                }
+
+                // Shortens the String to the proper length.
+                password.truncate(length);
+
+                Ok(password)
+            }
+            Self::V3 => {
+
+                // Turns the hex back into the raw digest bytes, in a buffer
+                // that is sized once so it never leaves a copy behind.
+                let mut key = KeyBuffer::with_capacity(hashed.len() / 2);
+                for pair in hashed.chunks(2) {
+                    let byte = std::str::from_utf8(pair).ok()
+                        .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                        .ok_or(PassgenError::EncodingFailure)?;
+                    key.push(byte);
+                }
+
+                match encoder {
+                    Encoder::Policy(policy, alphabet) => policy.generate(&key, alphabet, length),
+                    Encoder::Alphabet(alphabet) => Ok(alphabet.encode(&key, length)),
+                    _ => Err(PassgenError::EncodingFailure),
+                }
+            }
+            Self::V4 | Self::V5 => {
+
+                // V4 reads the raw digests, V5 every byte from SHAKE256 of the one key.
+                let mut stream = match self {
+                    Self::V5 => ByteStream::xof(hashed),
+                    _ => ByteStream::new(hashed),
+                };
+
+                match encoder {
+                    Encoder::Alphabet(alphabet) => Ok(alphabet.encode_stream(&mut stream, length)),
+                    Encoder::Policy(policy, alphabet) => policy.generate_stream(&mut stream, alphabet, length),
+                    Encoder::Passphrase(passphrase) => Ok(passphrase.generate(&mut stream, length)),
+                    Encoder::Template(template) => Ok(template.generate(&mut stream)),
+                    Encoder::Pin(pin) => Ok(pin.generate(&mut stream, length)),
+                }
+            }
+        }
+    }
+}
+
+// Writes bytes onto the end of `output` as uppercase hex, with no String in
+// between to be left in memory.
+fn push_hex(output: &mut Vec<u8>, bytes: &[u8]) {
+
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+    for byte in bytes {
+        output.push(DIGITS[usize::from(byte >> 4)]);
+        output.push(DIGITS[usize::from(byte & 0x0f)]);
+    }
+}
+
+impl Default for Algorithm {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = PassgenError;
+
+    // Accepts the identifier printed by id().
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "npg0" => Ok(Self::V0),
+            "npg1" => Ok(Self::V1),
+            "npg2" => Ok(Self::V2),
+            "npg3" => Ok(Self::V3),
+            "npg4" => Ok(Self::V4),
+            "npg5" => Ok(Self::V5),
+            _ => Err(PassgenError::UnknownAlgorithm(input.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::kdf::IteratedSha512;
+
+    // Tests that every identifier parses back to its algorithm.
+    #[test]
+    fn id_round_trip() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.id().parse::<Algorithm>(), Ok(algorithm));
+        }
+
+        assert_eq!("npg9".parse::<Algorithm>(),
+            Err(PassgenError::UnknownAlgorithm(String::from("npg9"))));
+    }
+
+    // Tests that every version still gives its known answers.
+    #[test]
+    fn known_answers() {
+        assert_eq!(self_test(), Ok(()));
+
+        // Every version has answers of its own.
+        for algorithm in Algorithm::ALL {
+            assert!(KNOWN_ANSWERS.iter().any(|answer| answer.0 == algorithm), "{}", algorithm);
+        }
+
+        // Every version with raw digests has a passphrase, a template and a PIN.
+        for algorithm in Algorithm::ALL.into_iter().filter(Algorithm::supports_raw_digests) {
+            for mode in ["words", "long", "pin"] {
+                assert!(KNOWN_ENCODINGS.iter().any(|answer| answer.0 == algorithm && answer.1 == mode),
+                    "{} {}", algorithm, mode);
+            }
+        }
+
+        // The newest version is the last one.
+        assert_eq!(Algorithm::ALL.last(), Some(&Algorithm::LATEST));
+    }
+
+    // Hashes one chunk with iterated SHA-512.
+    fn chunk(algorithm: Algorithm, seed: &str, length: u64, hashes: u64, index: u64) -> Vec<u8> {
+        let mut output = Vec::new();
+        algorithm.hash_chunk(&IteratedSha512, seed, length, hashes, index, &mut output).unwrap();
+        output
+    }
+
+    // Tests that V2 rounds are chained, not one longer message.
+    #[test]
+    fn v2_is_iterated() {
+
+        let once = chunk(Algorithm::V2, "apple", 32, 1, 0);
+
+        // Hashing the first digest again by hand.
+        let first = Sha512::digest(b"apple320");
+        let twice = format!("{:X}", Sha512::digest(first)).into_bytes();
+
+        assert_eq!(chunk(Algorithm::V2, "apple", 32, 2, 0), twice);
+        assert_ne!(once, twice);
+
+        // A count of 0 still hashes once.
+        assert_eq!(chunk(Algorithm::V2, "apple", 32, 0, 0), once);
+    }
+
+    // Tests that V4 keeps the raw digest of its binary chunk input.
+    #[test]
+    fn v4_chunk_is_raw() {
+
+        let mut input = Vec::new();
+        input.extend_from_slice(&5u64.to_be_bytes());
+        input.extend_from_slice(b"apple");
+        input.extend_from_slice(&32u64.to_be_bytes());
+        input.extend_from_slice(&7u64.to_be_bytes());
+        let comparitor = Sha512::digest(&input).to_vec();
+
+        assert_eq!(chunk(Algorithm::V4, "apple", 32, 1, 7), comparitor);
+
+        // "apple1" with length 2 and "apple" with length 12 both hash
+        // "apple123" as chunk 3 in V3, but not in V4.
+        assert_eq!(chunk(Algorithm::V3, "apple1", 2, 1, 3), chunk(Algorithm::V3, "apple", 12, 1, 3));
+        assert_ne!(chunk(Algorithm::V4, "apple1", 2, 1, 3), chunk(Algorithm::V4, "apple", 12, 1, 3));
+    }
+
+    // Tests that every encoder is refused by the versions that can't make
+    // it, at lengths it can't have and in prefix mode, with its own error.
+    #[test]
+    fn check_encoding_refuses_conflicts() {
+
+        use crate::wordlist::Wordlist;
+
+        let alphabet = Encoder::Alphabet(Alphabet::new("ab").unwrap());
+        let policy = Encoder::default().with_policy(Policy::new());
+        let passphrase = Encoder::Passphrase(Passphrase::new(Wordlist::parse("apple\nbanana").unwrap()));
+
+        // A list with a word that starts another, and one where a word and a
+        // hyphen start another.
+        let joined = |text, separator| {
+            let mut passphrase = Passphrase::new(Wordlist::parse(text).unwrap());
+            passphrase.set_separator(separator);
+            Encoder::Passphrase(passphrase)
+        };
+        let (prefixed, hyphenated) = (joined("apple\napplesauce", ""), joined("yo\nyo-yo", "-"));
+        let template = Encoder::Template(Template::preset("medium").unwrap());
+        let pin = Encoder::Pin(Pin::new());
+
+        let invalid = || String::new();
+        let conflicts = [
+            (Algorithm::V2, &alphabet, false, 8, PassgenError::InvalidAlphabet(invalid())),
+            (Algorithm::V2, &policy, false, 8, PassgenError::InvalidPolicy(invalid())),
+            (Algorithm::V4, &Encoder::default(), true, 8, PassgenError::UnsupportedPrefix(invalid())),
+            (Algorithm::V3, &policy, true, 8, PassgenError::UnsupportedPrefix(invalid())),
+            (Algorithm::V5, &policy, true, 8, PassgenError::InvalidPolicy(invalid())),
+            (Algorithm::V3, &passphrase, false, 6, PassgenError::InvalidPassphrase(invalid())),
+            (Algorithm::V5, &passphrase, true, 6, PassgenError::InvalidPassphrase(invalid())),
+            (Algorithm::V5, &prefixed, false, 6, PassgenError::InvalidPassphrase(invalid())),
+            (Algorithm::V5, &hyphenated, false, 6, PassgenError::InvalidPassphrase(invalid())),
+            (Algorithm::V5, &passphrase, false, passphrase::MAX_WORDS + 1, PassgenError::Overflow(invalid())),
+            (Algorithm::V3, &template, false, 8, PassgenError::InvalidTemplate(invalid())),
+            (Algorithm::V5, &template, false, 9, PassgenError::InvalidTemplate(invalid())),
+            (Algorithm::V5, &template, true, 8, PassgenError::InvalidTemplate(invalid())),
+            (Algorithm::V3, &pin, false, 6, PassgenError::InvalidPin(invalid())),
+            (Algorithm::V5, &pin, false, 3, PassgenError::InvalidPin(invalid())),
+            (Algorithm::V5, &pin, false, 9, PassgenError::InvalidPin(invalid())),
+            (Algorithm::V5, &pin, true, 6, PassgenError::InvalidPin(invalid())),
+        ];
+
+        for (algorithm, encoder, prefix, length, comparitor) in conflicts {
+            let error = algorithm.check_encoding(encoder, prefix, length).unwrap_err();
+            assert_eq!(std::mem::discriminant(&error), std::mem::discriminant(&comparitor),
+                "{} {:?} {} {}", algorithm, encoder, prefix, length);
+        }
+
+        // Every encoder can be made by the versions with raw digests.
+        for algorithm in [Algorithm::V4, Algorithm::V5] {
+            for (encoder, length) in [(&alphabet, 8), (&policy, 8), (&passphrase, 6), (&template, 8), (&pin, 4), (&pin, 8)] {
+                assert_eq!(algorithm.check_encoding(encoder, false, length), Ok(()), "{} {:?}", algorithm, encoder);
+            }
+        }
+        assert_eq!(Algorithm::V5.check_encoding(&alphabet, true, 8), Ok(()));
+
+        // The same lists are fine once the words can be told apart.
+        assert_eq!(Algorithm::V5.check_encoding(&joined("apple\napplesauce", "-"), false, 6), Ok(()));
+        assert_eq!(Algorithm::V5.check_encoding(&joined("apple\nyo-yo", " "), false, 6), Ok(()));
+    }
+
+    // Tests that V5 stretches the seed once, whatever the length.
+    #[test]
+    fn v5_has_one_chunk() {
+
+        assert_eq!(Algorithm::V5.chunk_size(), u64::MAX);
+        assert_eq!(Algorithm::V5.chunk_count(4000), 1);
+        assert_eq!(chunk(Algorithm::V5, "apple", 32, 1, 0), chunk(Algorithm::V4, "apple", 32, 1, 0));
+
+        // Reads the whole password from SHAKE256 of the key.
+        let key = chunk(Algorithm::V5, "apple", 4000, 1, 0);
+        let encoder = Encoder::default();
+        let password = Algorithm::V5.encode(&key, &encoder, false, 4000).unwrap();
+
+        assert_eq!(password.len(), 4000);
+        assert_ne!(password, Algorithm::V4.encode(&key, &encoder, false, 4000).unwrap());
+    }
+
+    // Runs the original hash_base94() through HashArguments with V0.
+    fn hash_base94(seed: &str, length: u64, hashes: u64) -> String {
+
+        use crate::hashing_arguments::HashArguments;
+
+        let mut args = HashArguments::new(String::from(seed), hashes, length, 1).unwrap();
+        args.set_algorithm(Algorithm::V0);
+        args.hash().unwrap();
+        args.encode().unwrap();
+        args.finish()
+    }
+
+    // The tests below are the known answers of the original hash_base94().
+
+    // Tests that V0 hashes as many chunks as hash_base94() did.
+    #[test]
+    fn v0_chunk_count() {
+
+        for (length, comparitor) in [(1, 1), (32, 1), (74, 1), (75, 2), (149, 2), (150, 3)] {
+            assert_eq!(Algorithm::V0.chunk_count(length), comparitor, "{}", length);
+        }
+
+        assert_eq!(hash_base94("apple", 75, 1).len(), 75);
+    }
+
+    // Tests that the hashing function produces the correct ouput
+    // for a given input.
+    #[test]
+    fn v0_hash_output() {
+
+        // Processed hash inputs for output.
+        let output = hash_base94("apple", 32, 1);
+
+        // Correct output for hash.
+        let comparitor = String::from("<#*6'Y:[tndK3%T`qtD$(C`eIS])]A6?");
+
+        // Tests that processed output and correct output are the same.
+        assert_eq!(output, comparitor);
+    }
+
+    #[test]
+    fn v0_hash_default_length() {
+
+        // Set of arguments processed.
+        let output = hash_base94("hello", 32, 1);
+
+        // Correct output for comparison.
+        let comparitor = String::from("|#auduHx~Lm>V00&2Pu{O;]rd-QZT+|:");
+
+        // Tests that the length is correct.
+        assert_eq!(output.len(), 32);
+
+        // Tests that the output is correct.
+        assert_eq!(comparitor, output);
+    }
+
+    #[test]
+    fn v0_hash_custom_length() {
+
+        // Set of arguments processed.
+        let output = hash_base94("peanut butter", 20, 1);
+
+        // Correct output
+        let comparitor = String::from("&%dWJ|:iD3q)'X'r(vOy");
+
+        assert_eq!(output.len(), 20);
+
+        assert_eq!(output, comparitor);
+    }
+
+    #[test]
+    fn v0_hash_custom_count() {
+
+        // Processed input arguments.
+        let output = hash_base94("banana", 32, 6);
+
+        // Correct output
+        let comparitor = String::from(r"Q1!0.cxUpSJcc@m4y-PEX~O=nRZ0_5{3");
+
+        // Incorrect output
+        let bad_comparitor = String::from(r"nTX$_F\o>hv8HxHXMU#~vm|vp|Up>7kC");
+
+        // Tests for count of 1 instead.
+        assert_ne!(output, bad_comparitor);
+        
+        // Tests for correct output.
+        assert_eq!(output, comparitor);
+    }
+
+    #[test]
+    fn v0_hash_custom_length_count() {
+
+        // Output arguments.
+        let output = hash_base94("foo", 55, 8);
+
+        // Correct output
+        let comparitor = String::from(
+            "4i`M~Mf9r7Tk`]N;q6t'lpuN(/~qFC?V9u5&=tMO}4#m!$gBcBZqr<a");
+
+        // Incorrect output
+        let bad_comparitor = String::from(
+            ")NHu`+PqgbtUfn#7f7Ugia]'H]|ux{-M*wajer;EXg==I&R~^4U&&M$");
+
+        // Tests for count of 1 instead.
+        assert_ne!(output, bad_comparitor);
+
+        // Tests for correct output.
+        assert_eq!(output, comparitor);
+    }
+
+    #[test]
+    fn v0_hash_length_change() {
+
+        let mut output_1 = hash_base94("carrot", 32, 1);
+        let mut output_2 = hash_base94("carrot", 33, 1);
+
+        output_1.truncate(32);
+        output_2.truncate(32);
+
+        // Tests that they are not identical.
+        assert_ne!(output_1, output_2);
+    }
+
+    #[test]
+    fn v0_hash_length_longer() {
+
+        let output = hash_base94("hello", 500, 1);
+
+        assert_eq!(output.len(), 500);
+    }
+}
@@ -0,0 +1,259 @@
+// Maps hash bytes onto any set of characters without bias.
+//
+// Every character is picked with rejection sampling: a random number is only
+// used when it falls below the largest multiple of the alphabet size, so each
+// character is exactly as likely as every other one.
+
+use crate::error::PassgenError;
+
+// SHA512 hashes binary data
+use sha2::{Sha512, Digest};
+
+// SHAKE256 turns one key into as many bytes as are needed.
+use sha3::Shake256;
+use sha3::digest::{self, ExtendableOutput, XofReader};
+
+// Wipes every block read from the key once it is used up, and locks it in
+// memory if the program asks for it.
+use crate::secret::KeyBuffer;
+
+// The most bytes a block of the stream holds: one SHAKE256 block, which is
+// longer than a SHA-512 digest.
+const BLOCK_SIZE: usize = 136;
+
+// The characters a password can be made of.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Alphabet {
+    chars: Vec<char>
+}
+
+impl Alphabet {
+
+    // Builds an alphabet from every character in the String, in order.
+    // Rejects alphabets with fewer than 2 characters or with repeats.
+    pub fn new(chars: &str) -> Result<Self, PassgenError> {
+
+        let chars: Vec<char> = chars.chars().collect();
+
+        if chars.len() < 2 {
+            return Err(PassgenError::InvalidAlphabet(
+                String::from("it needs at least 2 characters")));
+        }
+
+        // Catches repeated characters, which would be picked more often.
+        for (index, c) in chars.iter().enumerate() {
+            if chars[..index].contains(c) {
+                return Err(PassgenError::InvalidAlphabet(format!("'{}' is repeated", c)));
+            }
+        }
+
+        Ok(Self { chars })
+    }
+
+    // The 94 printable ASCII characters, '!' through '~'.
+    pub fn printable() -> Self {
+        Self {
+            chars: ('!'..='~').collect()
+        }
+    }
+
+    // The number of characters in the alphabet.
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    // Whether the alphabet has no characters.
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    // The characters, in order.
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    // Maps the key onto `length` characters of the alphabet.
+    pub fn encode(&self, key: &[u8], length: usize) -> String {
+        self.encode_stream(&mut ByteStream::new(key), length)
+    }
+
+    // Maps the next bytes of the stream onto `length` characters.
+    pub(crate) fn encode_stream(&self, stream: &mut ByteStream, length: usize) -> String {
+
+        // Sized for the widest character, so the String never leaves a copy behind.
+        let widest = self.chars.iter().map(|c| c.len_utf8()).max().unwrap_or(1);
+        let mut password = String::with_capacity(length * widest);
+
+        password.extend((0..length).map(|_| self.chars[stream.uniform(self.chars.len())]));
+        password
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Self {
+        Self::printable()
+    }
+}
+
+// Reads bytes from a key, and extends the key with SHA-512 once it runs out
+// so rejection sampling can always finish. A stream made with xof() reads
+// every byte from SHAKE256 instead. The buffer is allocated once and
+// refilled in place, so no copy of the key is ever left unwiped.
+pub(crate) struct ByteStream<'a> {
+    key: &'a [u8],
+    buffer: KeyBuffer,
+    position: usize,
+    counter: u64,
+    reader: Option<<Shake256 as ExtendableOutput>::Reader>
+}
+
+impl<'a> ByteStream<'a> {
+
+    pub(crate) fn new(key: &'a [u8]) -> Self {
+
+        let mut buffer = KeyBuffer::with_capacity(key.len().max(BLOCK_SIZE));
+        buffer.extend_from_slice(key);
+
+        Self {
+            key,
+            buffer,
+            position: 0,
+            counter: 0,
+            reader: None
+        }
+    }
+
+    // Reads SHAKE256(key), which never runs out.
+    pub(crate) fn xof(key: &[u8]) -> Self {
+
+        let mut hasher = Shake256::default();
+        digest::Update::update(&mut hasher, key);
+
+        Self {
+            key: &[],
+            buffer: KeyBuffer::with_capacity(BLOCK_SIZE),
+            position: 0,
+            counter: 0,
+            reader: Some(hasher.finalize_xof())
+        }
+    }
+
+    // Returns the next byte.
+    pub(crate) fn next_byte(&mut self) -> u8 {
+
+        // Reads the next block of the XOF.
+        if let Some(reader) = &mut self.reader {
+            if self.position == self.buffer.len() {
+                self.buffer.resize(BLOCK_SIZE, 0);
+                reader.read(&mut self.buffer);
+                self.position = 0;
+            }
+        }
+
+        // Refills the buffer with SHA512(key || counter).
+        if self.position == self.buffer.len() {
+            let mut hasher = Sha512::new();
+            hasher.update(self.key);
+            hasher.update(self.counter.to_be_bytes());
+
+            self.buffer.resize(64, 0);
+            hasher.finalize_into(digest::generic_array::GenericArray::from_mut_slice(&mut self.buffer));
+            self.position = 0;
+            self.counter += 1;
+        }
+
+        self.position += 1;
+        self.buffer[self.position - 1]
+    }
+
+    // Returns a number in 0..bound, with every value equally likely.
+    pub(crate) fn uniform(&mut self, bound: usize) -> usize {
+
+        // Uses the fewest bytes that can hold every value below the bound.
+        let bytes = match bound {
+            0..=0x100 => 1,
+            0x101..=0x1_0000 => 2,
+            _ => 4,
+        };
+        let range = 1u64 << (8 * bytes);
+
+        // The largest multiple of the bound that fits in the range.
+        let limit = range - (range % bound as u64);
+
+        loop {
+            let mut value = 0u64;
+            for _ in 0..bytes {
+                value = (value << 8) | u64::from(self.next_byte());
+            }
+
+            if value < limit {
+                return (value % bound as u64) as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that values at or above the limit are skipped.
+    #[test]
+    fn uniform_rejects_high_values() {
+
+        // With a bound of 3 the limit is 255, so 255 is thrown away.
+        let key = [255, 4, 255, 255, 2];
+        let mut stream = ByteStream::new(&key);
+
+        assert_eq!(stream.uniform(3), 1);
+        assert_eq!(stream.uniform(3), 2);
+    }
+
+    // Tests that an xof() stream reads SHAKE256 straight through, across blocks.
+    #[test]
+    fn xof_reads_shake256() {
+
+        let mut comparitor = [0u8; 300];
+        Shake256::digest_xof(b"apple", &mut comparitor);
+
+        let mut stream = ByteStream::xof(b"apple");
+        let output: Vec<u8> = (0..300).map(|_| stream.next_byte()).collect();
+
+        assert_eq!(output, comparitor);
+    }
+
+    // Tests that every character shows up about as often as every other one.
+    #[test]
+    fn encode_is_uniform() {
+
+        let alphabet = Alphabet::new("abcdefg").unwrap();
+        let output = alphabet.encode(b"apple", 70000);
+
+        for c in alphabet.chars() {
+            let count = output.chars().filter(|o| o == c).count();
+
+            // Expected 10000 each. This allows for about 4 standard deviations.
+            assert!((9650..10350).contains(&count), "'{}' came up {} times", c, count);
+        }
+    }
+
+    // Tests that non-ASCII alphabets work.
+    #[test]
+    fn encode_unicode() {
+
+        let alphabet = Alphabet::new("äöü").unwrap();
+        let output = alphabet.encode(b"apple", 20);
+
+        assert_eq!(output.chars().count(), 20);
+        assert!(output.chars().all(|c| alphabet.chars().contains(&c)));
+    }
+
+    // Tests that bad alphabets are rejected.
+    #[test]
+    fn new_rejects_bad_alphabets() {
+        assert!(Alphabet::new("").is_err());
+        assert!(Alphabet::new("a").is_err());
+        assert!(Alphabet::new("abca").is_err());
+        assert_eq!(Alphabet::printable().len(), 94);
+    }
+}
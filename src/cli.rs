@@ -0,0 +1,224 @@
+// A small command-line parser. Each subcommand lists its positional
+// arguments and named options, and gets its --help text from that list.
+
+use std::collections::HashMap;
+
+// A named option. Options with an empty `value` are switches that take no value.
+pub struct Opt {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub value: &'static str,
+    pub help: &'static str,
+}
+
+// A subcommand and everything it accepts.
+pub struct Command {
+    pub name: &'static str,
+    pub about: &'static str,
+
+    // Names in [brackets] are optional and must come after the required ones.
+    pub positional: &'static [(&'static str, &'static str)],
+
+    // Groups of options, so common ones can be shared between commands.
+    pub options: &'static [&'static [Opt]],
+}
+
+// Why parsing stopped.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+
+    // --help was asked for.
+    Help,
+
+    // The arguments were wrong. Holds the reason.
+    Usage(String),
+}
+
+// The parsed arguments of one command.
+#[derive(Debug, Default)]
+pub struct Matches {
+    positional: Vec<String>,
+    values: HashMap<&'static str, String>,
+}
+
+impl Matches {
+
+    // The positional argument at `index`, if it was given.
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    // The value of an option by its long name, if it was given.
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values.get(long).map(String::as_str)
+    }
+
+    // Whether an option or switch was given.
+    pub fn has(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    // Parses an option's value as a whole number, or returns the default.
+    pub fn number(&self, long: &str, default: u64) -> Result<u64, ParseError> {
+        match self.value(long) {
+            Some(value) => value.parse::<u64>().map_err(|e| ParseError::Usage(
+                format!("{}. '--{}' value is not a valid integer", e, long))),
+            None => Ok(default),
+        }
+    }
+}
+
+impl Command {
+
+    // Finds an option by its long or short name.
+    fn find(&self, name: &str) -> Option<&Opt> {
+        self.options.iter().flat_map(|group| group.iter()).find(|opt| {
+            match name.strip_prefix("--") {
+                Some(long) => opt.long == long,
+                None => {
+                    let mut chars = name.chars().skip(1);
+                    opt.short.is_some() && chars.next() == opt.short && chars.next().is_none()
+                }
+            }
+        })
+    }
+
+    // Parses the arguments that come after the subcommand's name.
+    pub fn parse(&self, args: &[String]) -> Result<Matches, ParseError> {
+
+        let mut matches = Matches::default();
+        let mut args = args.iter();
+
+        while let Some(argument) = args.next() {
+
+            // Everything after "--" is positional, so seeds can start with '-'.
+            if argument == "--" {
+                matches.positional.extend(args.by_ref().cloned());
+                break;
+            }
+
+            if argument == "--help" || argument == "-h" {
+                return Err(ParseError::Help);
+            }
+
+            if !argument.starts_with('-') || argument == "-" {
+                matches.positional.push(argument.clone());
+                continue;
+            }
+
+            // Splits "--name=value" into its parts.
+            let (name, inline) = match argument.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (argument.as_str(), None),
+            };
+
+            let opt = self.find(name)
+                .ok_or_else(|| ParseError::Usage(format!("'{}' is not an option of '{}'", name, self.name)))?;
+
+            let value = if opt.value.is_empty() {
+                if inline.is_some() {
+                    return Err(ParseError::Usage(format!("'--{}' takes no value", opt.long)));
+                }
+                String::new()
+            } else {
+                match inline.or_else(|| args.next().cloned()) {
+                    Some(value) => value,
+                    None => return Err(ParseError::Usage(format!("'--{}' needs a value", opt.long))),
+                }
+            };
+
+            matches.values.insert(opt.long, value);
+        }
+
+        // Checks the number of positional arguments.
+        let required = self.positional.iter().filter(|(name, _)| !name.starts_with('[')).count();
+        if matches.positional.len() < required {
+            return Err(ParseError::Usage(format!("'{}' needs {} argument(s)", self.name, required)));
+        }
+        if matches.positional.len() > self.positional.len() {
+            return Err(ParseError::Usage(format!("'{}' got too many arguments", self.name)));
+        }
+
+        Ok(matches)
+    }
+
+    // The --help text for the command.
+    pub fn help(&self) -> String {
+
+        let mut text = format!("{}\n\nUsage: neo_passgen {} [options]", self.about, self.name);
+        for (name, _) in self.positional {
+            text.push(' ');
+            text.push_str(name);
+        }
+        text.push('\n');
+
+        if !self.positional.is_empty() {
+            text.push_str("\nArguments:\n");
+            for (name, help) in self.positional {
+                text.push_str(&format!("  {:<28} {}\n", name, help));
+            }
+        }
+
+        text.push_str("\nOptions:\n");
+        for opt in self.options.iter().flat_map(|group| group.iter()) {
+            let short = opt.short.map_or(String::from("    "), |short| format!("-{}, ", short));
+            let name = format!("{}--{} {}", short, opt.long, opt.value);
+            text.push_str(&format!("  {:<28} {}\n", name.trim_end(), opt.help));
+        }
+        text.push_str(&format!("  {:<28} {}\n", "-h, --help", "Prints this help"));
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST: Command = Command {
+        name: "test",
+        about: "A test command.",
+        positional: &[("<seed>", "The seed"), ("[extra]", "Something optional")],
+        options: &[&[
+            Opt { long: "length", short: Some('l'), value: "<n>", help: "Length" },
+            Opt { long: "quiet", short: Some('q'), value: "", help: "No output" },
+        ]],
+    };
+
+    fn args(input: &[&str]) -> Vec<String> {
+        input.iter().map(|argument| argument.to_string()).collect()
+    }
+
+    // Tests every way of passing an option.
+    #[test]
+    fn parse_options() {
+
+        for input in [&["apple", "--length", "9"][..], &["--length=9", "apple"], &["-l", "9", "apple"]] {
+            let matches = TEST.parse(&args(input)).unwrap();
+
+            assert_eq!(matches.positional(0), Some("apple"));
+            assert_eq!(matches.number("length", 32), Ok(9));
+            assert!(!matches.has("quiet"));
+        }
+
+        let matches = TEST.parse(&args(&["-q", "--", "--apple", "x"])).unwrap();
+        assert_eq!(matches.positional(0), Some("--apple"));
+        assert_eq!(matches.positional(1), Some("x"));
+        assert!(matches.has("quiet"));
+        assert_eq!(matches.number("length", 32), Ok(32));
+    }
+
+    // Tests that bad arguments are usage errors, not panics.
+    #[test]
+    fn parse_errors() {
+        assert_eq!(TEST.parse(&args(&["apple", "--help"])).unwrap_err(), ParseError::Help);
+
+        for input in [&[][..], &["a", "b", "c"], &["apple", "--length"], &["apple", "--colour", "red"],
+            &["apple", "--quiet=yes"], &["apple", "-x"]] {
+            assert!(matches!(TEST.parse(&args(input)), Err(ParseError::Usage(_))), "{:?}", input);
+        }
+
+        let matches = TEST.parse(&args(&["apple", "--length", "many"])).unwrap();
+        assert!(matches!(matches.number("length", 32), Err(ParseError::Usage(_))));
+    }
+}
@@ -0,0 +1,216 @@
+// A pool of worker threads that hashes many passwords, for example one for
+// every site in a batch. The threads are started once and reused, instead
+// of hash_multi() starting new ones for every password.
+//
+// Each job is hashed on a single worker, so a batch runs as many passwords
+// at once as there are workers. Results always come back in the order the
+// jobs were given, whichever worker finishes first.
+
+use crate::error::PassgenError;
+
+use crate::hashing_arguments::{self, HashArguments};
+
+// For the worker threads.
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+// Keeps a worker alive when a job panics.
+use std::panic::{self, AssertUnwindSafe};
+
+// Anything a worker can run.
+type Job = Box<dyn FnOnce() + Send>;
+
+// Owns the worker threads. They are stopped and joined when it is dropped.
+pub struct Engine {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>
+}
+
+impl Engine {
+
+    // Starts `threads` workers. Rejects a count of 0.
+    pub fn new(threads: u64) -> Result<Self, PassgenError> {
+
+        if threads == 0 {
+            return Err(PassgenError::InvalidThreadCount(threads));
+        }
+
+        let (sender, receiver) = mpsc::channel::<Job>();
+
+        // Every worker takes its next job from the one queue.
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let mut workers = Vec::new();
+
+        for a in 0..threads {
+            let receiver = Arc::clone(&receiver);
+
+            let worker = thread::Builder::new()
+                .name(format!("neo_passgen worker #{}", a))
+                .spawn(move || loop {
+
+                    // Holds the lock only while waiting, not while hashing.
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+
+                    // The queue closes when the Engine is dropped.
+                    match job {
+                        Ok(job) => {
+                            // A panic only loses this job, which its batch reports.
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => return,
+                    }
+                })
+                .map_err(|_| PassgenError::ThreadFailure)?;
+
+            workers.push(worker);
+        }
+
+        Ok(Self {
+            sender: Some(sender),
+            workers
+        })
+    }
+
+    // The number of workers.
+    pub fn threads(&self) -> u64 {
+        self.workers.len() as u64
+    }
+
+    // Hashes every job and returns their passwords in the same order.
+    // One job failing doesn't stop the others.
+    pub fn run_all(&self, jobs: Vec<HashArguments>) -> Vec<Result<String, PassgenError>> {
+
+        let count = jobs.len();
+
+        // Every job sends back its index with its password.
+        let (tx, rx) = mpsc::channel();
+
+        for (index, args) in jobs.into_iter().enumerate() {
+            let transmit = tx.clone();
+
+            let job: Job = Box::new(move || {
+
+                // The receiver only hangs up if run_all() itself has gone.
+                let _ = transmit.send((index, args.run_single()));
+            });
+
+            if let Some(sender) = &self.sender {
+                // Only fails if every worker is gone, which the holes below report.
+                let _ = sender.send(job);
+            }
+        }
+
+        std::mem::drop(tx);
+
+        // Puts each password in its job's place.
+        let mut results: Vec<Option<Result<String, PassgenError>>> = (0..count).map(|_| None).collect();
+
+        for (index, result) in rx {
+            results[index] = Some(result);
+        }
+
+        // A job that panicked never sent anything.
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or(Err(PassgenError::ThreadFailure)))
+            .collect()
+    }
+
+    // Hashes one job on the pool.
+    pub fn run(&self, job: HashArguments) -> Result<String, PassgenError> {
+        self.run_all(vec![job])
+            .pop()
+            .unwrap_or(Err(PassgenError::ThreadFailure))
+    }
+}
+
+impl Default for Engine {
+
+    // One worker for every core.
+    fn default() -> Self {
+        let threads = hashing_arguments::available_threads();
+
+        // Falls back to one worker if the system won't start that many.
+        Self::new(threads)
+            .or_else(|_| Self::new(1))
+            .unwrap_or(Self { sender: None, workers: Vec::new() })
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+
+        // Closes the queue, so every worker stops after its current job.
+        self.sender.take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::algorithm::Algorithm;
+
+    fn job(seed: &str, length: u64) -> HashArguments {
+        HashArguments::new(String::from(seed), 10, length, 1).unwrap()
+    }
+
+    // Tests that results come back in job order and match run().
+    #[test]
+    fn run_all_keeps_order() {
+
+        let engine = Engine::new(3).unwrap();
+
+        let seeds: Vec<String> = (0..20).map(|a| format!("site{}.example", a)).collect();
+        let comparitor: Vec<String> = seeds
+            .iter()
+            .enumerate()
+            .map(|(a, seed)| job(seed, 10 + a as u64 * 7).run().unwrap())
+            .collect();
+
+        // Runs the same engine twice to check the workers are reused.
+        for _batch in 0..2 {
+            let jobs = seeds.iter().enumerate().map(|(a, seed)| job(seed, 10 + a as u64 * 7)).collect();
+            let results: Vec<String> = engine.run_all(jobs).into_iter().map(Result::unwrap).collect();
+
+            assert_eq!(results, comparitor);
+        }
+
+        assert_eq!(engine.threads(), 3);
+    }
+
+    // Tests that one failing job leaves the others alone.
+    #[test]
+    fn run_all_reports_each_error() {
+
+        let engine = Engine::new(2).unwrap();
+
+        let mut legacy = job("apple", 32);
+        legacy.set_algorithm(Algorithm::V1);
+        legacy.set_alphabet(crate::alphabet::Alphabet::new("ab").unwrap());
+
+        let results = engine.run_all(vec![job("apple", 32), legacy, job("pear", 32)]);
+
+        assert_eq!(results[0], job("apple", 32).run());
+        assert!(matches!(results[1], Err(PassgenError::InvalidAlphabet(_))));
+        assert_eq!(results[2], job("pear", 32).run());
+
+        assert!(engine.run_all(Vec::new()).is_empty());
+    }
+
+    // Tests that an engine needs at least one worker.
+    #[test]
+    fn new_rejects_zero_threads() {
+        assert!(matches!(Engine::new(0), Err(PassgenError::InvalidThreadCount(0))));
+        assert!(Engine::default().threads() >= 1);
+    }
+}
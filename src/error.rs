@@ -0,0 +1,84 @@
+// The errors that can be returned by the library.
+
+use std::fmt;
+
+// Every way the hashing_arguments API can fail.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PassgenError {
+
+    // The thread count was 0, or the wrong count for the method called.
+    InvalidThreadCount(u64),
+
+    // The requested password length was 0.
+    ZeroLength,
+
+    // A value was too large to be handled, described by what it is.
+    Overflow(String),
+
+    // A hashing thread panicked or hung up before returning its chunks.
+    ThreadFailure,
+
+    // The hashed String could not be encoded to the requested length.
+    EncodingFailure,
+
+    // The algorithm identifier is not one this version knows about.
+    UnknownAlgorithm(String),
+
+    // A key derivation function or one of its parameters was rejected.
+    InvalidKdfParameters(String),
+
+    // The alphabet can't be used, or the algorithm doesn't support it.
+    InvalidAlphabet(String),
+
+    // The password policy can't be met, or the algorithm doesn't support it.
+    InvalidPolicy(String),
+
+    // A parameter string could not be read, or doesn't fit the other inputs.
+    InvalidParameters(String),
+
+    // A builder was asked to build before a required setting was given.
+    MissingSetting(String),
+
+    // Prefix mode was asked of an algorithm that can't make prefix passwords.
+    UnsupportedPrefix(String),
+
+    // The passphrase settings or wordlist can't be used, or the algorithm
+    // doesn't support passphrases.
+    InvalidPassphrase(String),
+
+    // A template has letters that aren't known, doesn't fit the length, or
+    // the algorithm doesn't support templates.
+    InvalidTemplate(String),
+
+    // A PIN was asked for with the wrong number of digits, with settings
+    // that only passwords have, or from an algorithm that can't make one.
+    InvalidPin(String),
+}
+
+impl fmt::Display for PassgenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidThreadCount(threads) => {
+                write!(f, "a thread count of {} is not valid here", threads)
+            }
+            Self::ZeroLength => write!(f, "the password length must be at least 1"),
+            Self::Overflow(value) => write!(f, "{} is too large", value),
+            Self::ThreadFailure => write!(f, "a hashing thread failed"),
+            Self::EncodingFailure => write!(f, "the hash could not be encoded"),
+            Self::UnknownAlgorithm(id) => write!(f, "'{}' is not a known algorithm", id),
+            Self::InvalidKdfParameters(reason) => {
+                write!(f, "invalid key derivation settings: {}", reason)
+            }
+            Self::InvalidAlphabet(reason) => write!(f, "invalid alphabet: {}", reason),
+            Self::InvalidPolicy(reason) => write!(f, "invalid password policy: {}", reason),
+            Self::InvalidParameters(reason) => write!(f, "invalid parameter string: {}", reason),
+            Self::MissingSetting(name) => write!(f, "the {} was never set", name),
+            Self::UnsupportedPrefix(id) => write!(f, "{} can't make prefix passwords", id),
+            Self::InvalidPassphrase(reason) => write!(f, "invalid passphrase: {}", reason),
+            Self::InvalidTemplate(reason) => write!(f, "invalid template: {}", reason),
+            Self::InvalidPin(reason) => write!(f, "invalid PIN: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PassgenError {}
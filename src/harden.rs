@@ -0,0 +1,238 @@
+// Keeps secrets out of swap and core dumps. The library already wipes
+// seeds once they are dropped, this covers the time they are alive.
+//
+// Every step is best effort: a failure is logged as a warning and the
+// program carries on, since a locked-down system can still refuse them.
+//
+// main() locks the seed, the master secret and the finished password
+// itself. The stretched key and the byte stream it is read through live
+// inside the library, which has no unsafe code, so it hands their buffers
+// to the hook lock_key_buffers() sets. Each chunk's 64-byte digest is still
+// on the stack for a moment before it is copied into a locked buffer.
+
+use log::{debug, warn};
+
+// Counts the locks on each page.
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use std::any::Any;
+
+use neo_passgen::secret;
+
+// How many MemoryLocks cover each locked page, by page number. Locks don't
+// stack: one munlock unpins a page however often it was locked, so a page
+// is only unlocked once the last MemoryLock on it is dropped.
+static LOCKED_PAGES: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+// Every password locks buffers of its own, so a refused lock is only
+// warned about once.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+// Stops the kernel from writing a core dump of the process, which would
+// hold the seed. On Linux it also stops other processes of the same user
+// from attaching to it or reading its memory.
+pub fn disable_core_dumps() {
+
+    #[cfg(unix)]
+    {
+        let limit = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+
+        // Lowering a limit is always allowed, so this rarely fails.
+        if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &limit) } != 0 {
+            warn!("core dumps could not be turned off: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+            warn!("the process could not be made undumpable: {}", std::io::Error::last_os_error());
+        }
+    }
+
+    #[cfg(not(unix))]
+    debug!("Core dumps can't be turned off on this system.");
+
+    #[cfg(unix)]
+    debug!("Turned off core dumps.");
+}
+
+// Has the library lock every buffer it keeps the stretched key in.
+pub fn lock_key_buffers() {
+    secret::set_lock_hook(lock_key);
+}
+
+fn lock_key(start: usize, length: usize) -> Box<dyn Any + Send + Sync> {
+    Box::new(MemoryLock::span(start, length, "a key buffer"))
+}
+
+// Pins the pages of a secret in RAM so they are never swapped to disk.
+// They are unpinned when this is dropped, so keep it alive for as long as
+// the secret's buffer is. Moving the buffer's owner is fine, the heap
+// memory itself doesn't move. Two secrets on the same page can be locked
+// and unlocked in any order.
+pub struct MemoryLock {
+    pages: Range<usize>
+}
+
+impl MemoryLock {
+
+    // Locks the bytes of `secret`. `name` is only used in the warning.
+    pub fn new(secret: &[u8], name: &str) -> Self {
+        Self::span(secret.as_ptr() as usize, secret.len(), name)
+    }
+
+    // Locks `length` bytes from the address `start`, which can be spare
+    // capacity nothing has been written to yet.
+    pub fn span(start: usize, length: usize, name: &str) -> Self {
+
+        // Nothing to unlock later unless the lock works.
+        let mut lock = Self {
+            pages: 0..0
+        };
+
+        if length == 0 {
+            return lock;
+        }
+
+        #[cfg(unix)]
+        {
+            let size = page_size();
+            let pages = start / size..(start + length - 1) / size + 1;
+
+            // Holds the count while locking, so no other lock can unlock the
+            // pages in between. Locking a page twice is harmless.
+            let mut counts = LOCKED_PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+            let length = (pages.end - pages.start) * size;
+
+            if unsafe { libc::mlock((pages.start * size) as *const libc::c_void, length) } == 0 {
+                for page in pages.clone() {
+                    *counts.entry(page).or_insert(0) += 1;
+                }
+
+                debug!("Locked {} in memory.", name);
+                lock.pages = pages;
+                return lock;
+            }
+
+            // mlock fails when RLIMIT_MEMLOCK is too low, among other things.
+            if WARNED.swap(true, Ordering::Relaxed) {
+                debug!("{} could not be locked in memory: {}", name, std::io::Error::last_os_error());
+            } else {
+                warn!("{} could not be locked in memory: {}", name, std::io::Error::last_os_error());
+            }
+        }
+
+        #[cfg(not(unix))]
+        if !WARNED.swap(true, Ordering::Relaxed) {
+            warn!("{} can't be locked in memory on this system", name);
+        }
+
+        lock
+    }
+}
+
+impl Drop for MemoryLock {
+    fn drop(&mut self) {
+
+        let mut counts = LOCKED_PAGES.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for page in self.pages.clone() {
+            let Some(count) = counts.get_mut(&page) else {
+                continue;
+            };
+
+            *count -= 1;
+            if *count > 0 {
+                continue;
+            }
+
+            // The last lock on the page is gone.
+            counts.remove(&page);
+
+            #[cfg(unix)]
+            {
+                let size = page_size();
+                unsafe { libc::munlock((page * size) as *const libc::c_void, size) };
+            }
+        }
+    }
+}
+
+// The size of a memory page.
+#[cfg(unix)]
+fn page_size() -> usize {
+
+    // Every system has a page size, but 4096 is the usual one if it can't be read.
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        size if size > 0 => size as usize,
+        _ => 4096,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that core dumps end up limited to 0 bytes.
+    #[cfg(unix)]
+    #[test]
+    fn core_limit_is_zero() {
+
+        disable_core_dumps();
+
+        let mut limit = libc::rlimit { rlim_cur: 1, rlim_max: 1 };
+        assert_eq!(unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut limit) }, 0);
+        assert_eq!(limit.rlim_cur, 0);
+    }
+
+    // Tests that locking never panics, whether or not the system allows it.
+    #[test]
+    fn lock_and_unlock() {
+
+        let secret = String::from("correct horse");
+        let lock = MemoryLock::new(secret.as_bytes(), "the test seed");
+        drop(lock);
+
+        drop(MemoryLock::new(&[], "nothing"));
+
+        // Spare capacity can be locked before anything is written to it.
+        let buffer: Vec<u8> = Vec::with_capacity(256);
+        drop(lock_key(buffer.as_ptr() as usize, buffer.capacity()));
+
+        // Runs here rather than in a test of its own, which could lock the
+        // same page at the same time.
+        #[cfg(unix)]
+        shared_page_stays_locked();
+    }
+
+    // Checks that a page stays counted until the last lock on it is dropped.
+    #[cfg(unix)]
+    fn shared_page_stays_locked() {
+
+        let secrets = String::from("first second");
+        let (first, second) = secrets.as_bytes().split_at(6);
+        let page = first.as_ptr() as usize / page_size();
+        let count = || LOCKED_PAGES.lock().unwrap().get(&page).copied().unwrap_or(0);
+
+        let first_lock = MemoryLock::new(first, "the first secret");
+
+        // Skips the rest where the system refuses to lock memory.
+        if first_lock.pages.is_empty() {
+            return;
+        }
+
+        let before = count();
+        let second_lock = MemoryLock::new(second, "the second secret");
+        assert_eq!(count(), before + 1);
+
+        drop(first_lock);
+        assert_eq!(count(), before);
+
+        drop(second_lock);
+        assert_eq!(count(), before - 1);
+    }
+}
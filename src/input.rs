@@ -0,0 +1,94 @@
+// Reads secrets from places that don't leak them. Arguments show up in
+// shell history and /proc/<pid>/cmdline, so seeds come from a hidden
+// prompt, a pipe, a file descriptor or a file instead.
+
+use std::fs;
+
+use std::io::{self, IsTerminal, Read};
+
+// Reads a secret from an open file descriptor, like `--seed-fd 3`.
+// Going through /dev/fd lets the kernel check the descriptor for us.
+pub fn from_fd(fd: u64) -> io::Result<String> {
+    if cfg!(unix) {
+        from_file(&format!("/dev/fd/{}", fd))
+    } else {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "file descriptors need a unix system"))
+    }
+}
+
+// Reads a secret from a file.
+pub fn from_file(path: &str) -> io::Result<String> {
+    Ok(trim_newline(fs::read_to_string(path)?))
+}
+
+// Reads a secret piped into stdin. Returns None when stdin is a terminal,
+// where the prompt should be used instead.
+pub fn from_stdin() -> io::Result<Option<String>> {
+
+    let mut stdin = io::stdin();
+
+    if stdin.is_terminal() {
+        return Ok(None);
+    }
+
+    let mut secret = String::new();
+    stdin.read_to_string(&mut secret)?;
+
+    Ok(Some(trim_newline(secret)))
+}
+
+// Asks for a secret on the terminal without echoing it. With `confirm`
+// it is asked for twice, so a typo can't make a password nobody can regenerate.
+pub fn prompt(label: &str, confirm: bool) -> io::Result<String> {
+
+    let secret = rpassword::prompt_password(format!("{}: ", label))?;
+
+    if confirm && rpassword::prompt_password(format!("{} again: ", label))? != secret {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the two entries did not match"));
+    }
+
+    Ok(secret)
+}
+
+// Drops the one line ending that files and `echo` put after a secret.
+// Anything else, including other whitespace, is part of the secret.
+fn trim_newline(mut secret: String) -> String {
+
+    if secret.ends_with('\n') {
+        secret.pop();
+
+        if secret.ends_with('\r') {
+            secret.pop();
+        }
+    }
+
+    secret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that only one line ending is removed.
+    #[test]
+    fn trim_one_newline() {
+        assert_eq!(trim_newline(String::from("apple\n")), "apple");
+        assert_eq!(trim_newline(String::from("apple\r\n")), "apple");
+        assert_eq!(trim_newline(String::from("apple\n\n")), "apple\n");
+        assert_eq!(trim_newline(String::from(" apple ")), " apple ");
+    }
+
+    // Tests reading a seed file.
+    #[test]
+    fn read_file() {
+
+        let path = std::env::temp_dir().join(format!("neo_passgen_seed_{}", std::process::id()));
+        fs::write(&path, "correct horse\n").unwrap();
+
+        let comparitor = from_file(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(comparitor.unwrap(), "correct horse");
+        assert!(from_file("/nonexistent/neo_passgen").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+// The key derivation functions that stretch each chunk of the seed.
+// Memory-hard functions make every guess at a seed expensive on GPUs.
+
+use crate::error::PassgenError;
+
+// SHA512 hashes binary data
+use sha2::{Sha512, Digest};
+
+use std::fmt;
+use std::sync::Arc;
+
+// The fixed salt. Chunks are already unique through their length and index.
+const SALT: &[u8] = b"neo_passgen/kdf";
+
+// Stretches one chunk of input into a 64 byte key.
+pub trait Kdf: Send + Sync + fmt::Debug {
+
+    // Identifier with every parameter that changes the output,
+    // for example "argon2id$m=65536,t=3,p=1".
+    fn id(&self) -> String;
+
+    // Derives the key. `hashcount` is the caller's iteration count, which
+    // the SHA-512 and PBKDF2 functions use as their number of rounds.
+    fn derive(&self, input: &[u8], hashcount: u64) -> Result<[u8; 64], PassgenError>;
+}
+
+// The iterated SHA-512 scheme. Each round hashes the last digest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IteratedSha512;
+
+impl Kdf for IteratedSha512 {
+    fn id(&self) -> String {
+        String::from("sha512")
+    }
+
+    fn derive(&self, input: &[u8], hashcount: u64) -> Result<[u8; 64], PassgenError> {
+
+        // The first round hashes the input itself.
+        let mut digest = Sha512::digest(input);
+
+        // Every following round hashes the last digest. A count of 0 acts as 1.
+        for _i in 1..hashcount {
+            digest = Sha512::digest(digest);
+        }
+
+        Ok(digest.into())
+    }
+}
+
+// PBKDF2-HMAC-SHA512 with `hashcount` rounds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Pbkdf2;
+
+impl Kdf for Pbkdf2 {
+    fn id(&self) -> String {
+        String::from("pbkdf2")
+    }
+
+    fn derive(&self, input: &[u8], hashcount: u64) -> Result<[u8; 64], PassgenError> {
+
+        // PBKDF2 needs at least one round and counts them in a u32.
+        let rounds = u32::try_from(hashcount.max(1))
+            .map_err(|_| PassgenError::Overflow(format!("a PBKDF2 round count of {}", hashcount)))?;
+
+        let mut output = [0u8; 64];
+        pbkdf2::pbkdf2_hmac::<Sha512>(input, SALT, rounds, &mut output);
+
+        Ok(output)
+    }
+}
+
+// scrypt with a cost of 2^log_n, block size r and parallelism p.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Scrypt {
+    log_n: u8,
+    r: u32,
+    p: u32
+}
+
+impl Scrypt {
+
+    // Checks the parameters before any hashing is done.
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, PassgenError> {
+        scrypt::Params::new(log_n, r, p, 64)
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        Ok(Self { log_n, r, p })
+    }
+}
+
+impl Default for Scrypt {
+    fn default() -> Self {
+        Self { log_n: 15, r: 8, p: 1 }
+    }
+}
+
+impl Kdf for Scrypt {
+    fn id(&self) -> String {
+        format!("scrypt$ln={},r={},p={}", self.log_n, self.r, self.p)
+    }
+
+    fn derive(&self, input: &[u8], _hashcount: u64) -> Result<[u8; 64], PassgenError> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, 64)
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        let mut output = [0u8; 64];
+        scrypt::scrypt(input, SALT, &params, &mut output)
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        Ok(output)
+    }
+}
+
+// Argon2id with m KiB of memory, t passes and p lanes.
+//
+// The lanes are part of the output, so p is a fixed parameter and is not
+// tied to the thread count. The threads still split the chunks between them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Argon2id {
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32
+}
+
+impl Argon2id {
+
+    // Checks the parameters before any hashing is done.
+    pub fn new(m_cost: u32, t_cost: u32, p_cost: u32) -> Result<Self, PassgenError> {
+        argon2::Params::new(m_cost, t_cost, p_cost, Some(64))
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        Ok(Self { m_cost, t_cost, p_cost })
+    }
+}
+
+impl Default for Argon2id {
+    fn default() -> Self {
+        Self { m_cost: 65536, t_cost: 3, p_cost: 1 }
+    }
+}
+
+impl Kdf for Argon2id {
+    fn id(&self) -> String {
+        format!("argon2id$m={},t={},p={}", self.m_cost, self.t_cost, self.p_cost)
+    }
+
+    fn derive(&self, input: &[u8], _hashcount: u64) -> Result<[u8; 64], PassgenError> {
+        let params = argon2::Params::new(self.m_cost, self.t_cost, self.p_cost, Some(64))
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        let hasher = argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            params);
+
+        let mut output = [0u8; 64];
+        hasher.hash_password_into(input, SALT, &mut output)
+            .map_err(|e| PassgenError::InvalidKdfParameters(e.to_string()))?;
+
+        Ok(output)
+    }
+}
+
+// Builds a KDF from the identifier printed by Kdf::id(). Parameters that
+// are left out use their defaults, so "argon2id" alone is valid.
+pub fn from_id(id: &str) -> Result<Arc<dyn Kdf>, PassgenError> {
+
+    // Splits the name from the parameter list.
+    let (name, params) = match id.split_once('$') {
+        Some((name, params)) => (name, params),
+        None => (id, ""),
+    };
+
+    // Collects the key=value parameters.
+    let mut values = Vec::new();
+    for pair in params.split(',').filter(|pair| !pair.is_empty()) {
+        match pair.split_once('=') {
+            Some((key, value)) => {
+                let value = value.parse::<u32>()
+                    .map_err(|_| PassgenError::InvalidKdfParameters(pair.to_owned()))?;
+                values.push((key, value));
+            }
+            None => return Err(PassgenError::InvalidKdfParameters(pair.to_owned())),
+        }
+    }
+
+    // Looks up a parameter, or rejects ones the KDF doesn't have.
+    let check = |allowed: &[&str]| -> Result<(), PassgenError> {
+        match values.iter().find(|(key, _)| !allowed.contains(key)) {
+            Some((key, _)) => Err(PassgenError::InvalidKdfParameters(key.to_string())),
+            None => Ok(()),
+        }
+    };
+    let get = |key: &str, default: u32| {
+        values.iter().rev().find(|(k, _)| *k == key).map_or(default, |(_, v)| *v)
+    };
+
+    match name {
+        "sha512" => {
+            check(&[])?;
+            Ok(Arc::new(IteratedSha512))
+        }
+        "pbkdf2" => {
+            check(&[])?;
+            Ok(Arc::new(Pbkdf2))
+        }
+        "scrypt" => {
+            check(&["ln", "r", "p"])?;
+            let default = Scrypt::default();
+            let log_n = u8::try_from(get("ln", default.log_n.into()))
+                .map_err(|_| PassgenError::InvalidKdfParameters(String::from("ln")))?;
+            Ok(Arc::new(Scrypt::new(log_n, get("r", default.r), get("p", default.p))?))
+        }
+        "argon2id" => {
+            check(&["m", "t", "p"])?;
+            let default = Argon2id::default();
+            Ok(Arc::new(Argon2id::new(
+                get("m", default.m_cost),
+                get("t", default.t_cost),
+                get("p", default.p_cost))?))
+        }
+        _ => Err(PassgenError::InvalidKdfParameters(name.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Formats a key the same way the chunks are stored.
+    fn hex(key: [u8; 64]) -> String {
+        key.iter().map(|byte| format!("{:02X}", byte)).collect()
+    }
+
+    // Tests PBKDF2 against an independent implementation (Python's hashlib).
+    #[test]
+    fn pbkdf2_known_answer() {
+
+        let output = Pbkdf2.derive(b"apple320", 1000).unwrap();
+
+        let comparitor = "94C2451749A44B02B7972B7B39F2144DA4DFE070B5AD3F451820204149FCC955\
+            0F26ADFB6593683187935FA526F8D354179515EA898D208C804D784DD9870D78";
+
+        assert_eq!(hex(output), comparitor);
+    }
+
+    // Tests scrypt against an independent implementation (Python's hashlib).
+    #[test]
+    fn scrypt_known_answer() {
+
+        let output = Scrypt::new(10, 8, 1).unwrap().derive(b"apple320", 1).unwrap();
+
+        let comparitor = "45E022713174614A5EC8598CFBD879A577D70E1289D5B3E620AA0D9A8AEFE4BD\
+            0C1EF494E06915ECA9B4F943F0D7F4DD0DF5CDF7015FCD7780A3EC6FFD2E64BA";
+
+        assert_eq!(hex(output), comparitor);
+    }
+
+    // Tests that every Argon2id parameter changes the key.
+    #[test]
+    fn argon2id_parameters_matter() {
+
+        let base = Argon2id::new(64, 1, 1).unwrap().derive(b"apple320", 1).unwrap();
+
+        assert_eq!(Argon2id::new(64, 1, 1).unwrap().derive(b"apple320", 1).unwrap(), base);
+        assert_ne!(Argon2id::new(128, 1, 1).unwrap().derive(b"apple320", 1).unwrap(), base);
+        assert_ne!(Argon2id::new(64, 2, 1).unwrap().derive(b"apple320", 1).unwrap(), base);
+        assert_ne!(Argon2id::new(64, 1, 2).unwrap().derive(b"apple320", 1).unwrap(), base);
+    }
+
+    // Tests that identifiers parse back to the same KDF.
+    #[test]
+    fn id_round_trip() {
+        for id in ["sha512", "pbkdf2", "scrypt$ln=10,r=8,p=2", "argon2id$m=256,t=2,p=4"] {
+            assert_eq!(from_id(id).unwrap().id(), id);
+        }
+
+        // Left out parameters take their defaults.
+        assert_eq!(from_id("argon2id").unwrap().id(), Argon2id::default().id());
+        assert_eq!(from_id("scrypt$r=4").unwrap().id(), "scrypt$ln=15,r=4,p=1");
+    }
+
+    // Tests that bad identifiers are errors.
+    #[test]
+    fn id_rejects_bad_input() {
+        for id in ["md5", "sha512$n=2", "argon2id$m=1", "argon2id$m", "scrypt$ln=300"] {
+            assert!(matches!(from_id(id), Err(PassgenError::InvalidKdfParameters(_))), "{}", id);
+        }
+    }
+}
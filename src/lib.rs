@@ -1,63 +1,122 @@
+// A public module for the library's error type.
+pub mod error;
+
+// A public module for the versioned hashing schemes.
+pub mod algorithm;
+
+// A public module for the key derivation functions.
+pub mod kdf;
+
+// A public module for per-site passwords from one master secret.
+pub mod site;
+
+// A public module for mapping hashes onto character sets.
+pub mod alphabet;
+
+// A public module for password rules.
+pub mod policy;
+
+// A public module for parsing the passwordrules syntax into a policy.
+pub mod rules;
+
+// A public module for parameter strings that describe how a password was made.
+pub mod params;
+
+// A public module for hashing many passwords on one pool of threads.
+pub mod engine;
+
+// A public module for the lists passphrases draw their words from.
+pub mod wordlist;
+
+// A public module for diceware style passphrases.
+pub mod passphrase;
+
+// A public module for password shapes like "Cvcvnocvcv".
+pub mod template;
+
+// A public module for numeric PINs.
+pub mod pin;
+
+// A public module for the buffers the stretched key is kept in.
+pub mod secret;
+
 // A public module for turning Strings into hashed Strings.
 pub mod hashing_arguments {
 
-    // SHA512 hashes binary data
-    use sha2::{Sha512, Digest};
+    // The error type returned by every fallible function.
+    use crate::error::PassgenError;
 
-    // Base94 converts data to base94 String
-    use base94::encode;
+    // The versioned hashing schemes.
+    use crate::algorithm::{Algorithm, Encoder};
 
-    // For multithreading.
-    use std::sync::mpsc;
-    use std::thread;
+    // The key derivation functions.
+    use crate::kdf::{Kdf, IteratedSha512};
 
-    // Automatically runs the backend for you.
-    pub fn autohash(
-        seed: String,
-        hashcount: u64,
-        length: u64,
-        threads: u64) -> String {
+    // Shares the Kdf between threads.
+    use std::sync::Arc;
 
-        println!("Starting autohash()...");
-    
-        let mut autotype = HashArguments::new(seed, hashcount, length, threads);
+    // The characters a password is made of.
+    use crate::alphabet::Alphabet;
 
-        println!("Finished building HashArguments type.");
+    // The rules a password has to follow.
+    use crate::policy::Policy;
 
-        if autotype.threads == 1 {
+    // Describes the settings as one String.
+    use crate::params::Params;
 
-            println!("Hashing singlethreaded...");
+    // Words instead of characters.
+    use crate::passphrase::{Passphrase, DEFAULT_SEPARATOR};
 
-            // Single threaded hashing.
-            autotype.hash();
+    // Numeric PINs.
+    use crate::pin::Pin;
 
-            println!("Done.");
+    // Progress messages. Callers choose where they go, if anywhere.
+    use log::{debug, trace};
 
-        } else {
+    // For multithreading.
+    use std::sync::mpsc;
+    use std::thread;
 
-            println!("Hashing multithreaded...");
-            
-            // Multithreaded hashing.
-            autotype.hash_multi();
+    // Wipes seeds and hashes from memory once they are dropped.
+    use zeroize::Zeroizing;
 
-            println!("Done.");
-        }
+    // Holds the hashed chunks, locked in memory if the program asks for it.
+    use crate::secret::KeyBuffer;
 
-        println!("Encoding...");
+    use std::fmt;
 
-        autotype.encode();
+    // The longest password that can be made, about a megabyte. The hashed
+    // chunks and the password both have to fit in memory, and nobody needs more.
+    pub const MAX_LENGTH: u64 = 1 << 20;
 
-        println!("Done");
+    // Automatically runs the backend for you.
+    pub fn autohash(
+        seed: String,
+        hashcount: u64,
+        length: u64,
+        threads: u64) -> Result<String, PassgenError> {
 
-        println!("Finishing...");
+        debug!("Starting autohash()...");
 
-        autotype.finish()
+        HashArguments::new(seed, hashcount, length, threads)?.run()
     }
 
-    // Creates a type for use in multithreading
-    #[derive(Clone, Debug)]
+    // The number of threads this machine can run at once, or 1 if that
+    // can't be found out.
+    pub fn available_threads() -> u64 {
+        thread::available_parallelism().map_or(1, |threads| threads.get() as u64)
+    }
+
+    // Creates a type for use in multithreading. Each SeedType owns a
+    // contiguous run of chunk indexes and hashes them the same way hash() does.
+    // Every thread shares one copy of the seed.
+    #[derive(Clone)]
     struct SeedType {
-        chunks: Vec<String>,
+        seed: Arc<Zeroizing<String>>,
+        hashed: KeyBuffer,
+        algorithm: Algorithm,
+        kdf: Arc<dyn Kdf>,
+        length: u64,
         index: u64,
         hashes: u64,
         num: u64
@@ -65,17 +124,22 @@ pub mod hashing_arguments {
 
     impl SeedType {
 
-        fn new(chunk: String, index: u64, hashes: u64, num: u64) -> Self {
-
-            // Creates an empty vector.
-            let mut vectorstring = Vec::new();
-
-            // Adds the string as the first part of the vector.
-            vectorstring.push(chunk);
+        fn new(
+            seed: Arc<Zeroizing<String>>,
+            algorithm: Algorithm,
+            kdf: Arc<dyn Kdf>,
+            length: u64,
+            index: u64,
+            hashes: u64,
+            num: u64) -> Self {
 
-            // Creates the type.
+            // Creates the type with no finished chunks yet.
             Self {
-                chunks: vectorstring,
+                seed,
+                hashed: KeyBuffer::default(),
+                algorithm,
+                kdf,
+                length,
                 index,
                 hashes,
                 num
@@ -94,155 +158,306 @@ pub mod hashing_arguments {
             self.num = input;
         }
         
-        fn hash_chunk(&mut self){ 
-
-            // Creates a set of seeds.
-            for a in 1..self.num {
-                self.chunks.push(self.chunks[0].clone() + &( self.index + a ).to_string());
+        fn hash_chunk(&mut self) -> Result<(), PassgenError> { 
+
+            // Sized up front so the buffer never moves and leaves a copy behind.
+            self.hashed = HashArguments::hashed_buffer(self.num);
+
+            // Hashes every chunk in this run, in order.
+            for a in 0..self.num {
+                self.algorithm.hash_chunk(
+                        self.kdf.as_ref(),
+                        &self.seed,
+                        self.length,
+                        self.hashes,
+                        self.index + a,
+                        &mut self.hashed)?;
             }
 
-            // Concatenates the index.
-            self.chunks[0] = self.chunks[0].clone() + &self.index.to_string();
-
-            // Hashes the input multiple times
-            for ref mut b in self.chunks.clone() {
-                   
-                // Creates hasher object.
-                let mut hasher = Sha512::new();
-
-                // Hashes the input multiple times
-                for _d in 0..self.hashes {
-                    hasher.update(&b);
-                }
-
-                // Returns the finished seed
-                *b = format!("{:X}", hasher.finalize());
-            }
+            Ok(())
+        }
 
+        fn finish(self) -> KeyBuffer {
+            self.hashed
         }
+    }
 
-        fn finish(self) -> String {
-            let mut output = String::new();
-            for chunk in self.chunks {
-                output = output + &chunk;
-            }
-            output
+    // Leaves the seed and chunks out, so they never end up in logs.
+    impl fmt::Debug for SeedType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("SeedType")
+                .field("seed", &"[redacted]")
+                .field("hashed", &format_args!("[{} bytes redacted]", self.hashed.len()))
+                .field("algorithm", &self.algorithm)
+                .field("kdf", &self.kdf)
+                .field("length", &self.length)
+                .field("index", &self.index)
+                .field("hashes", &self.hashes)
+                .field("num", &self.num)
+                .finish()
         }
     }
 
-    // The public type that is used to hash Strings.
+    // The public type that is used to hash Strings. The seed, the hashed
+    // chunks and the password are wiped from memory when they are dropped.
     pub struct HashArguments {
-        seed: String,
+        seed: Zeroizing<String>,
+        hashed: KeyBuffer,
         hashcount: u64,
         length: u64,
-        threads: u64
+        threads: u64,
+        algorithm: Algorithm,
+        kdf: Arc<dyn Kdf>,
+        encoder: Encoder,
+        prefix: bool
     }
 
     impl HashArguments {
         
-        // A simple constructor function for the type.
+        // A simple constructor function for the type. Rejects inputs
+        // that could never be hashed.
         pub fn new(
             seed: String, 
             hashcount: u64,
             length: u64,
             threads: u64
-            ) -> Self {
-            
-            Self {
-                seed,
+            ) -> Result<Self, PassgenError> {
+
+            // Sanity check for the inputs.
+            if threads == 0 {
+                return Err(PassgenError::InvalidThreadCount(threads));
+            } else if length == 0 {
+                return Err(PassgenError::ZeroLength);
+            }
+
+            // Checks that the hashed chunks and the password will fit in memory.
+            if length > MAX_LENGTH {
+                return Err(PassgenError::Overflow(
+                    format!("a password length of {} (at most {})", length, MAX_LENGTH)));
+            }
+
+            Ok(Self {
+                seed: Zeroizing::new(seed),
+                hashed: KeyBuffer::default(),
                 hashcount,
                 length,
                 threads,
+                algorithm: Algorithm::default(),
+                kdf: Arc::new(IteratedSha512),
+                encoder: Encoder::default(),
+                prefix: false,
+            })
+
+        }
+
+        // Starts a HashArgumentsBuilder, which names every setting.
+        pub fn builder() -> HashArgumentsBuilder {
+            HashArgumentsBuilder::new()
+        }
+
+        // Replaces the seed, so every other setting can be checked before
+        // the seed is asked for.
+        pub fn set_seed(&mut self, seed: String) {
+
+            // Sets the seed value.
+            self.seed = Zeroizing::new(seed);
+        }
+
+        // Picks the hashing scheme. Defaults to Algorithm::DEFAULT.
+        pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+
+            // Sets the algorithm value.
+            self.algorithm = algorithm;
+        }
+
+        // Picks the function that stretches each chunk. Defaults to iterated SHA-512.
+        pub fn set_kdf(&mut self, kdf: Arc<dyn Kdf>) {
+
+            // Sets the kdf value.
+            self.kdf = kdf;
+        }
+
+        // Picks the characters the password is made of. Defaults to the 94
+        // printable ASCII characters, and only npg3 and newer can change it.
+        // Replaces a passphrase, template or PIN.
+        pub fn set_alphabet(&mut self, alphabet: Alphabet) {
+
+            // Sets the alphabet value.
+            self.encoder = std::mem::take(&mut self.encoder).with_alphabet(alphabet);
+        }
+
+        // Makes the password follow a policy. Only npg3 and newer support
+        // it. Replaces a passphrase, template or PIN.
+        pub fn set_policy(&mut self, policy: Policy) {
+
+            // Sets the policy value.
+            self.encoder = std::mem::take(&mut self.encoder).with_policy(policy);
+        }
+
+        // Picks what the key is turned into: a password, a passphrase, a
+        // template or a PIN. Defaults to a password of printable ASCII.
+        // Only npg4 and newer make anything but passwords.
+        pub fn set_encoder(&mut self, encoder: Encoder) {
+
+            // Sets the encoder value.
+            self.encoder = encoder;
+        }
+
+        // Makes every shorter password a prefix of the longer ones, so a site
+        // that cuts a password short still accepts it. Off by default, since
+        // normally each length gives an unrelated password. Only npg5 and
+        // newer support it, and never with a policy.
+        pub fn set_prefix(&mut self, prefix: bool) {
+
+            // Sets the prefix value.
+            self.prefix = prefix;
+        }
+
+        // Describes every setting that changes the password, so it can be
+        // regenerated from the seed and Params::hash_arguments().
+        pub fn params(&self) -> Params {
+
+            let passphrase = self.encoder.passphrase();
+
+            Params {
+                algorithm: self.algorithm,
+                kdf: self.algorithm.uses_kdf().then(|| self.kdf.id()),
+                hashcount: self.hashcount,
+                length: self.length,
+                alphabet: self.encoder.alphabet()
+                    .filter(|alphabet| **alphabet != Alphabet::default())
+                    .map(|alphabet| alphabet.chars().iter().collect()),
+                policy: self.encoder.policy().map(Policy::fingerprint),
+                prefix: self.prefix,
+                wordlist: passphrase.map(|passphrase| passphrase.wordlist().fingerprint().to_owned()),
+                separator: passphrase
+                    .map(Passphrase::separator)
+                    .filter(|separator| *separator != DEFAULT_SEPARATOR)
+                    .map(str::to_owned),
+                capitalize: passphrase.is_some_and(Passphrase::capitalize),
+                digit: passphrase.is_some_and(Passphrase::digit),
+                template: self.encoder.template().map(|template| template.id().to_owned()),
+                pin: self.encoder.pin().is_some(),
+                skip_common: self.encoder.pin().is_some_and(Pin::skip_common),
+            }
+        }
+
+        // Hashes, encodes and returns the password. Runs on as many threads
+        // as were allowed, but never on more than there are chunks or cores.
+        // The password is the same however many threads are used.
+        pub fn run(mut self) -> Result<String, PassgenError> {
+
+            let threads = self.threads.min(self.chunk_count()).min(available_threads());
+
+            if threads < 2 {
+                return self.run_single();
             }
 
+            debug!("Hashing on {} threads...", threads);
+            self.threads = threads;
+            self.hash_multi()?;
+
+            debug!("Encoding...");
+            self.encode()?;
+
+            Ok(self.finish())
+        }
+
+        // Hashes, encodes and returns the password on the calling thread,
+        // whatever thread count was set. Used by run() and by Engine workers.
+        pub(crate) fn run_single(mut self) -> Result<String, PassgenError> {
+
+            debug!("Hashing on one thread...");
+            self.threads = 1;
+            self.hash()?;
+
+            debug!("Encoding...");
+            self.encode()?;
+
+            Ok(self.finish())
         }
 
         // Hashes the type. This version runs in a single thread.
-        pub fn hash(&mut self) {
+        pub(crate) fn hash(&mut self) -> Result<(), PassgenError> {
 
-            // Sanity check for the inputs.
-            if self.threads == 0 {
-                panic!("A thread count of 0 is impossible.");
-            } else if self.threads != 1 {
-                panic!("This method is for single threaded hashing only.");
+            // Sanity check for the inputs. This method is for single threaded hashing only.
+            if self.threads != 1 {
+                return Err(PassgenError::InvalidThreadCount(self.threads));
             }
 
-            let chunk_count: u64;
-
             // Calculates the number of chunks needed.
-            if ( self.length % 35 ) != 0 {
-                chunk_count = ( self.length / 35 ) + 1;
-            } else {
-                chunk_count = self.length / 35;
-            } 
+            let chunk_count = self.chunk_count();
 
-            // A basic chunk of seed for the hasher.
-            let seed_chunk = self.seed.clone() + &self.length.to_string();
+            // Takes the seed out, so it is wiped once hashing is done.
+            let seed = std::mem::take(&mut self.seed);
 
-            // Clears the seed String.
-            self.seed.clear();
+            // Leaves room for every hashed chunk, so the buffer never moves
+            // and leaves a copy behind.
+            self.hashed = Self::hashed_buffer(chunk_count);
 
-            // Hashes clones of the seed_chunk into the String.
+            // Hashes each chunk of the seed into the buffer.
             for index in 0..chunk_count {
-                self.seed.push_str(&Self::hash_chunk(seed_chunk.clone(), self.hashcount, index));
+                self.algorithm.hash_chunk(
+                        self.kdf.as_ref(),
+                        &seed,
+                        self.hashed_length(),
+                        self.hashcount,
+                        index,
+                        &mut self.hashed)?;
             }
+
+            Ok(())
         }
 
-         // Hashes the type. This version is multithreaded.
-        pub fn hash_multi(&mut self) {
+        // Hashes the type. This version is multithreaded.
+        // The output is identical to hash() for every thread count.
+        pub(crate) fn hash_multi(&mut self) -> Result<(), PassgenError> {
 
-            println!("Starting hash_multi()...");
+            debug!("Starting hash_multi()...");
 
-            // Sanity check for the inputs.
-            if self.threads == 0 {
-                panic!("A thread count of 0 is impossible.");
-            } else if self.threads == 1 {
-                panic!("This method is for multithreaded hashing only.");
+            // Sanity check for the inputs. This method is for multithreaded hashing only.
+            if self.threads < 2 {
+                return Err(PassgenError::InvalidThreadCount(self.threads));
             }
 
-            println!("Checked thread count.");
-
-            let chunk_count: u64;
+            trace!("Checked thread count.");
 
             // Calculates the number of chunks needed.
-            if ( self.length % 35 ) != 0 {
-                chunk_count = ( self.length / 35 ) + 1;
-            } else {
-                chunk_count = self.length / 35;
-            }
+            let chunk_count = self.chunk_count();
 
-            println!("Calculated chunk count: {}", chunk_count);
+            debug!("Calculated chunk count: {}", chunk_count);
 
             if self.threads > chunk_count {
-                self.threads = chunk_count;
-                println!("Reduced thread count due to length being short.");
+                self.threads = chunk_count.max(1);
+                debug!("Reduced thread count due to length being short.");
             }
 
-            println!("Checked thread count again.");
+            trace!("Checked thread count again.");
 
-            println!("Thread count: {}", self.threads);
+            debug!("Thread count: {}", self.threads);
 
             // Calculates chunk count per thread.
             let thread_chunk_count: u64 = chunk_count / self.threads;
 
-            println!("thread_chunk_count: {}", thread_chunk_count);
+            trace!("thread_chunk_count: {}", thread_chunk_count);
 
+            // The first thread_remainder_count threads take one extra chunk.
             let thread_remainder_count: u64 = chunk_count % self.threads;
 
-            println!("thread_remainder_count: {}", thread_remainder_count);
+            trace!("thread_remainder_count: {}", thread_remainder_count);
 
-            // Creates a seed_type.
+            // Creates a seed_type. The seed moves into it rather than being copied.
             let seed = SeedType::new(
-                self.seed.clone() + &self.length.to_string(),
+                Arc::new(std::mem::take(&mut self.seed)),
+                self.algorithm,
+                Arc::clone(&self.kdf),
+                self.hashed_length(),
                 0,
                 self.hashcount,
                 0
                 );
 
-            println!("Created seed type.");
-
-            // Clears the seed String.
-            self.seed.clear();
+            trace!("Created seed type.");
 
             // MULTITHREADING
             // Creates a transmitter to receive types.
@@ -251,35 +466,34 @@ pub mod hashing_arguments {
             // Catches all thread handles.
             let mut handles = Vec::new();
 
-            // Catches the finished chunks.
+            // Catches the finished chunks, in thread order.
             let mut chunks_done = Vec::new();
 
-            println!("Setting up threads...");
+            trace!("Setting up threads...");
 
             // Creates chunks of seed_type and pushes them to a thread.
-            for a in 1..self.threads {
+            for a in 0..self.threads {
 
                 // Creates clone of SeedType.
                 let mut chunk = seed.clone();
 
-                // Sets the index and num.
-                chunk.set_index(a * thread_chunk_count);
-                chunk.set_num(thread_chunk_count);
+                // Sets the first index and the number of chunks for this thread.
+                chunk.set_index(a * thread_chunk_count + a.min(thread_remainder_count));
+                chunk.set_num(thread_chunk_count + u64::from(a < thread_remainder_count));
 
                 // Clones the transmitter.
                 let transmit = tx.clone();
 
-                // Copied index.
-                let b = a;
-
-                println!("Set up thread #{}", a);
+                trace!("Set up thread #{}", a);
 
                 // Creates a handle and starts a thread.
                 let handle = thread::spawn(move || {
-                    println!("Starting thread #{}", b);
-                    chunk.hash_chunk();
-                    transmit.send(chunk).unwrap();
-                    println!("Finished thread #{}", b);
+                    trace!("Starting thread #{}", a);
+                    let result = chunk.hash_chunk().map(|_| chunk);
+
+                    // The receiver only hangs up on failure, which is caught below.
+                    let _ = transmit.send((a, result));
+                    trace!("Finished thread #{}", a);
                 });
 
                 // Catches the handles.
@@ -288,59 +502,80 @@ pub mod hashing_arguments {
 
             std::mem::drop(tx);
 
-            println!("Caught thread handles");
+            trace!("Caught thread handles");
 
-            println!("Allocating space...");
+            trace!("Allocating space...");
 
             // Allocates space for chunks.
             for _b in 0..self.threads {
                 chunks_done.push(seed.clone());
             }
 
-            println!("Catching chunks...");
+            trace!("Catching chunks...");
+
+            // Counts the threads that sent back their chunks.
+            let mut recieved_count: u64 = 0;
+
+            // Keeps the first error a thread hit while hashing.
+            let mut first_error = None;
 
             // Catches all finished threads.
-            for recieved in rx {
-                println!("rx got: {:?}", recieved);
-                let index = recieved.index / thread_chunk_count;
-                chunks_done[index as usize] = recieved;
-                println!("rx Done.");
+            for (index, recieved) in rx {
+                trace!("rx got chunk set #{}", index);
+                match recieved {
+                    Ok(chunk) => chunks_done[index as usize] = chunk,
+                    Err(e) => { first_error.get_or_insert(e); }
+                }
+                recieved_count += 1;
+                trace!("rx Done.");
             }
 
-            println!("Shutting down threads...");
+            trace!("Shutting down threads...");
 
             // Closes all threads.
             for c in handles {
-                c.join().unwrap();
+                c.join().map_err(|_| PassgenError::ThreadFailure)?;
             }
 
-            println!("Combining chunks...");
+            // Passes on a hashing error from any of the threads.
+            if let Some(e) = first_error {
+                return Err(e);
+            }
 
-            // Hashes clones of the seed_chunk into the String.
+            // A thread that never sent its chunks would leave a hole in the password.
+            if recieved_count != self.threads {
+                return Err(PassgenError::ThreadFailure);
+            }
+
+            debug!("Combining chunks...");
+
+            // Joins every thread's chunks, in order.
+            self.hashed = Self::hashed_buffer(chunk_count);
             for seed in chunks_done {
-                self.seed.push_str(&seed.finish());
+                self.hashed.extend_from_slice(&seed.finish());
             }
             
-            println!("Done.");
-        }
+            trace!("Done.");
 
-        // TODO!
-        // Accepts a chunk of the seed and hashes it.
-        fn hash_chunk(mut seed_chunk: String, hashes: u64, index: u64) -> String {
-
-            // Creates hasher object.
-            let mut hasher = Sha512::new();
+            Ok(())
+        }
 
-            // Concatenates the index.
-            seed_chunk = seed_chunk + &index.to_string();
+        // Calculates the number of chunks the algorithm needs for the length.
+        fn chunk_count(&self) -> u64 {
+            self.algorithm.chunk_count(self.length)
+        }
 
-            // Hashes the input multiple times
-            for _i in 0..hashes {
-                hasher.update(&seed_chunk);
-            }
+        // The length the chunks are made unique for. Prefix mode uses 0,
+        // which no other password can have, so the key is the same for
+        // every length.
+        fn hashed_length(&self) -> u64 {
+            if self.prefix { 0 } else { self.length }
+        }
 
-            // Returns the finished seed
-            format!("{:X}", hasher.finalize())
+        // An empty buffer with room for every hashed chunk. No chunk is
+        // longer than 128 bytes, and new() checked the length.
+        fn hashed_buffer(chunk_count: u64) -> KeyBuffer {
+            KeyBuffer::with_capacity(chunk_count as usize * 128)
         }
 
         // TODO!
@@ -349,21 +584,551 @@ pub mod hashing_arguments {
         //
         //}
 
-        // Encodes the String in base94.
-        pub fn encode(&mut self) {
+        // Encodes the String with the algorithm's encoder, shortened to the length.
+        pub(crate) fn encode(&mut self) -> Result<(), PassgenError> {
+
+            let length = usize::try_from(self.length)
+                .map_err(|_| PassgenError::Overflow(format!("a password length of {}", self.length)))?;
             
             // Encodes the String.
-            self.seed = encode(&self.seed.clone().into_bytes(), 94);
+            let password = self.algorithm.encode(&self.hashed, &self.encoder, self.prefix, length)?;
+            self.seed = Zeroizing::new(password);
 
-            // Shortens the String to the proper length.
-            self.seed.truncate(self.length as usize);
+            // Wipes the hashes now that the password is made.
+            self.hashed = KeyBuffer::default();
+
+            Ok(())
         }
 
         // Destructor function. Encodes and returns just the String.
-        pub fn finish(self) -> String {
+        pub(crate) fn finish(mut self) -> String {
 
-            // Returns just the seed.
-            self.seed
+            // Returns just the seed. The caller owns the password from here.
+            std::mem::take(&mut *self.seed)
         }
-    } 
+    }
+
+    // Leaves the seed out, so it never ends up in logs.
+    impl fmt::Debug for HashArguments {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("HashArguments")
+                .field("seed", &"[redacted]")
+                .field("hashcount", &self.hashcount)
+                .field("length", &self.length)
+                .field("threads", &self.threads)
+                .field("algorithm", &self.algorithm)
+                .field("kdf", &self.kdf)
+                .field("encoder", &self.encoder)
+                .field("prefix", &self.prefix)
+                .finish()
+        }
+    }
+
+    // Builds a HashArguments from named settings, so numbers can't be
+    // swapped by accident. Every setting is checked by build(), rather than
+    // when the type is hashed.
+    //
+    //     let args = HashArguments::builder()
+    //         .seed(seed)
+    //         .iterations(10_000)
+    //         .length(32)
+    //         .build()?;
+    pub struct HashArgumentsBuilder {
+        seed: Option<Zeroizing<String>>,
+        iterations: Option<u64>,
+        length: Option<u64>,
+        threads: Option<u64>,
+        algorithm: Algorithm,
+        kdf: Option<Arc<dyn Kdf>>,
+        encoder: Encoder,
+        prefix: bool
+    }
+
+    impl HashArgumentsBuilder {
+
+        // The seed, iterations and length have to be set, unless a template
+        // gives the length. The rest defaults to every core, the default
+        // algorithm, iterated SHA-512, the printable ASCII alphabet, no
+        // policy, no prefix mode and a password rather than a passphrase
+        // or a PIN.
+        pub fn new() -> Self {
+            Self {
+                seed: None,
+                iterations: None,
+                length: None,
+                threads: None,
+                algorithm: Algorithm::default(),
+                kdf: None,
+                encoder: Encoder::default(),
+                prefix: false
+            }
+        }
+
+        // Sets the secret the password is made from.
+        pub fn seed(mut self, seed: String) -> Self {
+            self.seed = Some(Zeroizing::new(seed));
+            self
+        }
+
+        // Sets the rounds of hashing for each chunk, the hashcount.
+        pub fn iterations(mut self, iterations: u64) -> Self {
+            self.iterations = Some(iterations);
+            self
+        }
+
+        // Sets the length of the password.
+        pub fn length(mut self, length: u64) -> Self {
+            self.length = Some(length);
+            self
+        }
+
+        // Sets the threads to hash with. Never changes the password.
+        pub fn threads(mut self, threads: u64) -> Self {
+            self.threads = Some(threads);
+            self
+        }
+
+        // Sets the hashing scheme.
+        pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+            self.algorithm = algorithm;
+            self
+        }
+
+        // Sets the function that stretches each chunk. Only npg2 and newer use one.
+        pub fn kdf(mut self, kdf: Arc<dyn Kdf>) -> Self {
+            self.kdf = Some(kdf);
+            self
+        }
+
+        // Sets the characters the password is made of. Only npg3 and newer
+        // can change it. Replaces a passphrase, template or PIN.
+        pub fn alphabet(mut self, alphabet: Alphabet) -> Self {
+            self.encoder = self.encoder.with_alphabet(alphabet);
+            self
+        }
+
+        // Sets the rules the password has to follow. Only npg3 and newer
+        // support it. Replaces a passphrase, template or PIN.
+        pub fn policy(mut self, policy: Policy) -> Self {
+            self.encoder = self.encoder.with_policy(policy);
+            self
+        }
+
+        // Sets what the key is turned into. A template also sets the length
+        // unless one is given. Only npg4 and newer make anything but passwords.
+        pub fn encoder(mut self, encoder: Encoder) -> Self {
+            self.encoder = encoder;
+            self
+        }
+
+        // Makes shorter passwords prefixes of longer ones. Only npg5 and newer support it.
+        pub fn prefix(mut self, prefix: bool) -> Self {
+            self.prefix = prefix;
+            self
+        }
+
+        // Checks every setting and builds the HashArguments.
+        pub fn build(mut self) -> Result<HashArguments, PassgenError> {
+
+            let mut seed = self.seed.take()
+                .ok_or_else(|| PassgenError::MissingSetting(String::from("seed")))?;
+            let iterations = self.iterations
+                .ok_or_else(|| PassgenError::MissingSetting(String::from("iteration count")))?;
+            let length = self.length
+                .or(self.encoder.template().map(|template| template.len() as u64))
+                .ok_or_else(|| PassgenError::MissingSetting(String::from("length")))?;
+
+            // Checks the thread count, the length and that it fits in memory.
+            let threads = self.threads.unwrap_or_else(available_threads);
+            let mut args = HashArguments::new(std::mem::take(&mut *seed), iterations, length, threads)?;
+
+            // Checks that the algorithm supports everything it was given.
+            if self.kdf.is_some() && !self.algorithm.uses_kdf() {
+                return Err(PassgenError::InvalidKdfParameters(
+                    format!("{} does not use a key derivation function", self.algorithm)));
+            }
+            self.algorithm.check_encoding(&self.encoder, self.prefix, length)?;
+
+            if let Some(policy) = self.encoder.policy() {
+                policy.check_length(length)?;
+            }
+
+            args.set_algorithm(self.algorithm);
+            if let Some(kdf) = self.kdf {
+                args.set_kdf(kdf);
+            }
+            args.set_encoder(self.encoder);
+            args.set_prefix(self.prefix);
+
+            Ok(args)
+        }
+    }
+
+    impl Default for HashArgumentsBuilder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    // Leaves the seed out, so it never ends up in logs.
+    impl fmt::Debug for HashArgumentsBuilder {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("HashArgumentsBuilder")
+                .field("seed", &self.seed.as_ref().map(|_| "[redacted]"))
+                .field("iterations", &self.iterations)
+                .field("length", &self.length)
+                .field("threads", &self.threads)
+                .field("algorithm", &self.algorithm)
+                .field("kdf", &self.kdf)
+                .field("encoder", &self.encoder)
+                .field("prefix", &self.prefix)
+                .finish()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        // Runs the whole pipeline on a set of inputs.
+        fn run(
+            algorithm: Algorithm,
+            seed: &str,
+            hashcount: u64,
+            length: u64,
+            threads: u64) -> String {
+
+            let mut args = HashArguments::new(String::from(seed), hashcount, length, threads).unwrap();
+            args.set_algorithm(algorithm);
+
+            if threads == 1 {
+                args.hash().unwrap();
+            } else {
+                args.hash_multi().unwrap();
+            }
+
+            args.encode().unwrap();
+            args.finish()
+        }
+
+        // Tests that the legacy V1 output is unchanged for a given input.
+        #[test]
+        fn hash_output_v1() {
+
+            let output = run(Algorithm::V1, "apple", 1, 32, 1);
+
+            // Correct output for hash.
+            let comparitor = String::from(r"d\H8lE\REmqP-kiP0*jzrQpXm3TtX}KS");
+
+            assert_eq!(output, comparitor);
+        }
+
+        // Tests that the iterated V2 output is correct for a given input.
+        #[test]
+        fn hash_output_v2() {
+
+            let output = run(Algorithm::V2, "apple", 1000, 32, 1);
+
+            // Correct output for hash.
+            let comparitor = String::from(r"?X<FMd`8(.ccJ@Uq,<{}m*&0-I!>{f}{");
+
+            assert_eq!(output, comparitor);
+        }
+
+        // Tests that the V3 output is correct for a given input.
+        #[test]
+        fn hash_output_v3() {
+
+            let output = run(Algorithm::V3, "apple", 1000, 32, 1);
+
+            // Correct output for hash.
+            let comparitor = String::from(r"m&qQbw8Q;s%L|e3pAxN}A\VNGs=&/`Gr");
+
+            assert_eq!(output, comparitor);
+        }
+
+        // Tests that V3 only uses the alphabet it is given, and that older
+        // algorithms refuse to change it.
+        #[test]
+        fn encode_with_alphabet() {
+
+            let mut args = HashArguments::new(String::from("apple"), 10, 100, 1).unwrap();
+            args.set_alphabet(Alphabet::new("0123456789abcdef").unwrap());
+            args.hash().unwrap();
+            args.encode().unwrap();
+
+            let output = args.finish();
+            assert_eq!(output.len(), 100);
+            assert!(output.chars().all(|c| c.is_ascii_hexdigit()));
+
+            let mut legacy = HashArguments::new(String::from("apple"), 10, 100, 1).unwrap();
+            legacy.set_algorithm(Algorithm::V2);
+            legacy.set_alphabet(Alphabet::new("0123456789abcdef").unwrap());
+            legacy.hash().unwrap();
+            assert!(matches!(legacy.encode(), Err(PassgenError::InvalidAlphabet(_))));
+        }
+
+        // Tests that a policy is followed through the whole pipeline.
+        #[test]
+        fn encode_with_policy() {
+
+            use crate::policy::CharClass;
+
+            let mut policy = Policy::new();
+            policy.require(CharClass::Digit, 3);
+            policy.forbid("!\"'`\\");
+
+            let mut args = HashArguments::new(String::from("apple"), 10, 20, 1).unwrap();
+            args.set_policy(policy);
+            args.hash().unwrap();
+            args.encode().unwrap();
+
+            let output = args.finish();
+            assert_eq!(output.len(), 20);
+            assert!(output.chars().filter(char::is_ascii_digit).count() >= 3, "{}", output);
+            assert!(!output.contains(|c| "!\"'`\\".contains(c)), "{}", output);
+        }
+
+        // Tests that autohash() uses the default algorithm.
+        #[test]
+        fn autohash_uses_default() {
+
+            let output = autohash(String::from("apple"), 1000, 32, 1).unwrap();
+
+            assert_eq!(output, run(Algorithm::DEFAULT, "apple", 1000, 32, 1));
+        }
+
+        // Tests that every thread count gives the same password as a single thread,
+        // including lengths that leave remainder chunks.
+        #[test]
+        fn hash_multi_matches_hash() {
+            for algorithm in Algorithm::ALL {
+                for length in [1, 34, 35, 36, 70, 100, 211, 500] {
+
+                    let comparitor = run(algorithm, "apple", 3, length, 1);
+
+                    assert_eq!(comparitor.len(), length as usize);
+
+                    for threads in 2..9 {
+                        assert_eq!(run(algorithm, "apple", 3, length, threads), comparitor,
+                            "{} length {} threads {}", algorithm, length, threads);
+                    }
+                }
+            }
+        }
+
+        // Tests that every kdf gives the same password on any thread count,
+        // and that changing the kdf changes the password.
+        #[test]
+        fn hash_multi_matches_hash_with_kdf() {
+
+            use crate::kdf::{Pbkdf2, Scrypt, Argon2id};
+
+            let kdfs: [Arc<dyn Kdf>; 3] = [
+                Arc::new(Pbkdf2),
+                Arc::new(Scrypt::new(4, 8, 1).unwrap()),
+                Arc::new(Argon2id::new(64, 1, 1).unwrap()),
+            ];
+
+            let default = run(Algorithm::V2, "apple", 10, 100, 1);
+
+            for kdf in kdfs {
+
+                let hash_with = |threads| {
+                    let mut args = HashArguments::new(String::from("apple"), 10, 100, threads).unwrap();
+                    args.set_kdf(Arc::clone(&kdf));
+
+                    if threads == 1 {
+                        args.hash().unwrap();
+                    } else {
+                        args.hash_multi().unwrap();
+                    }
+
+                    args.encode().unwrap();
+                    args.finish()
+                };
+
+                let comparitor = hash_with(1);
+
+                assert_ne!(comparitor, default, "{}", kdf.id());
+                assert_eq!(hash_with(3), comparitor, "{}", kdf.id());
+            }
+        }
+
+        // Tests that impossible inputs are rejected when the type is built.
+        #[test]
+        fn new_rejects_bad_inputs() {
+
+            let zero_threads = HashArguments::new(String::from("apple"), 1, 32, 0);
+            assert_eq!(zero_threads.err(), Some(PassgenError::InvalidThreadCount(0)));
+
+            let zero_length = HashArguments::new(String::from("apple"), 1, 0, 1);
+            assert_eq!(zero_length.err(), Some(PassgenError::ZeroLength));
+
+            let huge_length = HashArguments::new(String::from("apple"), 1, u64::MAX, 1);
+            assert!(matches!(huge_length, Err(PassgenError::Overflow(_))));
+
+            assert!(HashArguments::new(String::from("apple"), 1, MAX_LENGTH, 1).is_ok());
+            let too_long = HashArguments::new(String::from("apple"), 1, MAX_LENGTH + 1, 1);
+            assert_eq!(too_long.err().map(|e| e.to_string()),
+                Some(format!("a password length of {} (at most {}) is too large", MAX_LENGTH + 1, MAX_LENGTH)));
+        }
+
+        // Tests that the builder makes the same password as new() and the setters.
+        #[test]
+        fn builder_matches_new() {
+
+            let comparitor = run(Algorithm::V2, "apple", 10, 100, 1);
+
+            let mut args = HashArguments::builder()
+                .seed(String::from("apple"))
+                .iterations(10)
+                .length(100)
+                .threads(2)
+                .algorithm(Algorithm::V2)
+                .build()
+                .unwrap();
+
+            args.hash_multi().unwrap();
+            args.encode().unwrap();
+
+            assert_eq!(args.finish(), comparitor);
+        }
+
+        // Tests that run() gives the same password whatever threads it picks.
+        #[test]
+        fn run_matches_hash() {
+            for length in [1, 35, 36, 200] {
+                let comparitor = run(Algorithm::DEFAULT, "apple", 10, length, 1);
+
+                for threads in [1, 2, 3, 64] {
+                    let args = HashArguments::new(String::from("apple"), 10, length, threads).unwrap();
+                    assert_eq!(args.run().unwrap(), comparitor, "{} threads, length {}", threads, length);
+                }
+            }
+
+            assert!(available_threads() >= 1);
+        }
+
+        // Tests that build() catches missing and conflicting settings.
+        #[test]
+        fn builder_rejects_bad_settings() {
+
+            let apple = || HashArguments::builder().seed(String::from("apple")).iterations(1).length(32);
+
+            let missing = HashArguments::builder().iterations(1).length(32).build();
+            assert_eq!(missing.err(), Some(PassgenError::MissingSetting(String::from("seed"))));
+
+            let missing = HashArguments::builder().seed(String::from("apple")).iterations(1).build();
+            assert_eq!(missing.err(), Some(PassgenError::MissingSetting(String::from("length"))));
+
+            assert_eq!(apple().threads(0).build().err(), Some(PassgenError::InvalidThreadCount(0)));
+            assert_eq!(apple().length(0).build().err(), Some(PassgenError::ZeroLength));
+
+            let kdf_on_v1 = apple().algorithm(Algorithm::V1).kdf(Arc::new(IteratedSha512)).build();
+            assert!(matches!(kdf_on_v1, Err(PassgenError::InvalidKdfParameters(_))));
+
+            let alphabet_on_v2 = apple().algorithm(Algorithm::V2).alphabet(Alphabet::new("ab").unwrap()).build();
+            assert!(matches!(alphabet_on_v2, Err(PassgenError::InvalidAlphabet(_))));
+
+            let mut policy = Policy::new();
+            policy.set_max_length(16);
+            assert!(matches!(apple().policy(policy).build(), Err(PassgenError::InvalidPolicy(_))));
+        }
+
+        // Tests that in prefix mode a shorter password starts every longer one,
+        // and that it is a different password from the normal one.
+        #[test]
+        fn prefix_passwords_are_prefixes() {
+
+            let prefixed = |length| {
+                let mut args = HashArguments::new(String::from("carrot"), 10, length, 1).unwrap();
+                args.set_algorithm(Algorithm::V5);
+                args.set_prefix(true);
+                args.run().unwrap()
+            };
+
+            let comparitor = prefixed(200);
+
+            for length in [1, 31, 32, 33, 64, 65, 199] {
+                assert_eq!(prefixed(length), comparitor[..length as usize]);
+            }
+
+            // Without prefix mode the lengths share nothing.
+            let normal = |length| run(Algorithm::LATEST, "carrot", 10, length, 1);
+            assert_ne!(normal(32), normal(33)[..32]);
+            assert_ne!(normal(32), prefixed(32));
+        }
+
+        // Tests that the setters are checked when the password is encoded.
+        #[test]
+        fn encode_checks_setters() {
+
+            let mut args = HashArguments::new(String::from("apple"), 1, 32, 1).unwrap();
+            args.set_algorithm(Algorithm::V3);
+            args.set_prefix(true);
+            assert!(matches!(args.run(), Err(PassgenError::UnsupportedPrefix(_))));
+        }
+
+        // A small list for the passphrase tests.
+        fn fruit() -> Passphrase {
+            Passphrase::new(crate::wordlist::Wordlist::parse("apple\nbanana\ncherry\ndamson").unwrap())
+        }
+
+        // Tests that a passphrase has as many words as the length, all from the list.
+        #[test]
+        fn passphrase_has_length_words() {
+
+            for algorithm in [Algorithm::V4, Algorithm::V5] {
+                let mut passphrase = fruit();
+                passphrase.set_separator(" ");
+
+                let mut args = HashArguments::new(String::from("apple"), 10, 6, 1).unwrap();
+                args.set_algorithm(algorithm);
+                args.set_encoder(Encoder::Passphrase(passphrase.clone()));
+
+                let comparitor = args.run().unwrap();
+                assert_eq!(comparitor.split(' ').count(), 6);
+                assert!(comparitor.split(' ').all(|word| passphrase.wordlist().words().iter().any(|known| known == word)));
+
+                // The same settings always make the same passphrase.
+                let again = HashArguments::builder().seed(String::from("apple")).iterations(10).length(6)
+                    .algorithm(algorithm).encoder(Encoder::Passphrase(passphrase)).build().unwrap();
+                assert_eq!(again.run().unwrap(), comparitor);
+            }
+        }
+
+        // Tests that Debug never shows the seed or the hashes.
+        #[test]
+        fn debug_redacts_secrets() {
+
+            let mut args = HashArguments::new(String::from("correct horse"), 1, 32, 1).unwrap();
+            assert!(!format!("{:?}", args).contains("correct horse"));
+
+            args.hash().unwrap();
+            let hashed = format!("{:?}", &args.hashed[..8]);
+            assert!(!format!("{:?}", args).contains(hashed.trim_end_matches(']')));
+
+            let seed = SeedType::new(
+                Arc::new(Zeroizing::new(String::from("correct horse"))),
+                Algorithm::default(),
+                Arc::new(IteratedSha512),
+                32,
+                0,
+                1,
+                1);
+            assert!(!format!("{:?}", seed).contains("correct horse"));
+        }
+
+        // Tests that calling the wrong hashing method is an error, not a panic.
+        #[test]
+        fn hash_wrong_thread_count() {
+
+            let mut single = HashArguments::new(String::from("apple"), 1, 32, 4).unwrap();
+            assert_eq!(single.hash(), Err(PassgenError::InvalidThreadCount(4)));
+
+            let mut multi = HashArguments::new(String::from("apple"), 1, 32, 1).unwrap();
+            assert_eq!(multi.hash_multi(), Err(PassgenError::InvalidThreadCount(1)));
+        }
+    }
 }
@@ -0,0 +1,74 @@
+// Sends log messages to stderr, so stdout only ever holds the password
+// and scripts can capture it with $(...).
+//
+// Nothing secret is ever logged: messages name steps, counts and indexes,
+// never seeds, hashes or passwords.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// How much is written to stderr.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verbosity {
+
+    // Only errors.
+    Quiet,
+
+    // Errors, warnings and what the user needs, like the parameter string.
+    Normal,
+
+    // Also the steps of each command.
+    Verbose,
+
+    // Also every thread and chunk.
+    Trace,
+}
+
+impl Verbosity {
+
+    // The most detailed level that is shown.
+    fn filter(self) -> LevelFilter {
+        match self {
+            Self::Quiet => LevelFilter::Error,
+            Self::Normal => LevelFilter::Info,
+            Self::Verbose => LevelFilter::Debug,
+            Self::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+struct Stderr;
+
+impl Log for Stderr {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+
+        // Leaves out messages from dependencies.
+        metadata.level() <= log::max_level() && metadata.target().starts_with("neo_passgen")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            Level::Error => eprintln!("Error: {}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            Level::Info => eprintln!("{}", record.args()),
+            Level::Debug => eprintln!("[debug] {}", record.args()),
+            Level::Trace => eprintln!("[trace] {}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Stderr = Stderr;
+
+// Starts logging at the given verbosity.
+pub fn init(verbosity: Verbosity) {
+
+    // This only fails when a logger is already set, and then that one is used.
+    let _ = log::set_logger(&LOGGER);
+
+    log::set_max_level(verbosity.filter());
+}
@@ -1,30 +1,665 @@
-use neo_passgen::hashing_arguments;
+// The command-line interface. Every subcommand is described by a
+// cli::Command below, which also gives its --help text.
+mod cli;
 
-//use neo_passgen::hashing_arguments::HashArguments;
+// Reads seeds from a prompt, a pipe, a file descriptor or a file.
+mod input;
+
+// Writes log messages to stderr.
+mod logger;
+
+// Keeps the seed and the stretched key out of swap and core dumps.
+mod harden;
+
+use harden::MemoryLock;
+
+use logger::Verbosity;
+
+use log::{debug, info};
+
+use cli::{Command, Matches, Opt, ParseError};
+
+use neo_passgen::error::PassgenError;
+
+use neo_passgen::algorithm::{self, Algorithm, Encoder};
+
+use neo_passgen::kdf;
+
+use neo_passgen::hashing_arguments::{self, HashArguments};
+
+use neo_passgen::site::SiteRequest;
+
+use neo_passgen::alphabet::Alphabet;
+
+use neo_passgen::rules;
+
+use neo_passgen::params::Params;
+
+use neo_passgen::passphrase::Passphrase;
+
+use neo_passgen::wordlist::Wordlist;
+
+use neo_passgen::template::Template;
+
+use neo_passgen::pin::Pin;
+
+// Wipes the password once it is printed.
+use zeroize::Zeroizing;
 
 use std::env;
 
-fn main() {
+// Used for the exit() function.
+use std::process;
+
+// Times the bench command.
+use std::time::{Duration, Instant};
+
+// Exit code for missing or unparsable command-line arguments.
+const USAGE_EXIT_CODE: i32 = 2;
+
+// Exit code when verify gets a different password.
+const MISMATCH_EXIT_CODE: i32 = 13;
+
+// Exit code when selftest finds a broken algorithm.
+const SELFTEST_EXIT_CODE: i32 = 14;
+
+// Exit code when the seed, or another secret, can't be read.
+const SEED_EXIT_CODE: i32 = 15;
+
+// Defaults for the hashing options.
+const DEFAULT_HASHCOUNT: u64 = 10_000;
+const DEFAULT_LENGTH: u64 = 32;
+
+// The options every hashing command takes.
+const HASH_OPTIONS: &[Opt] = &[
+    Opt { long: "hashcount", short: Some('n'), value: "<n>",
+        help: "Rounds of hashing for each chunk [default: 10000]" },
+    Opt { long: "length", short: Some('l'), value: "<n>",
+        help: "Length of the password [default: 32, or the nearest length --rules allows]" },
+    Opt { long: "threads", short: Some('t'), value: "<n>",
+        help: "Most threads to hash with, never changes the password [default: every core]" },
+    Opt { long: "algorithm", short: Some('a'), value: "<id>",
+        help: "npg0 to npg5 [default: npg5]" },
+    Opt { long: "kdf", short: Some('k'), value: "<id>",
+        help: "sha512, pbkdf2, 'scrypt$ln=15,r=8,p=1' or 'argon2id$m=65536,t=3,p=1' [default: sha512]" },
+    Opt { long: "alphabet", short: None, value: "<chars>",
+        help: "Characters the password is made of [default: printable ASCII]" },
+    Opt { long: "rules", short: None, value: "<rules>",
+        help: "Site rules like 'minlength: 8; required: digit; max-consecutive: 2'" },
+    Opt { long: "template", short: None, value: "<shape>",
+        help: "maximum, long, medium, basic, pin or a shape like Cvcvnocvcv, instead of --length" },
+    Opt { long: "prefix", short: None, value: "",
+        help: "Makes every shorter password the start of the longer ones, for sites that cut passwords short" },
+    Opt { long: "params", short: Some('p'), value: "<string>",
+        help: "A parameter string printed by an earlier run, instead of the options above" },
+];
+
+// Diceware style passphrases instead of passwords.
+const PASSPHRASE_OPTIONS: &[Opt] = &[
+    Opt { long: "words", short: Some('w'), value: "<n>",
+        help: "Makes a passphrase of this many words from the EFF large wordlist, instead of --length" },
+    Opt { long: "wordlist", short: None, value: "<path>",
+        help: "Draws the words from this file instead, one word or dice roll and word on each line" },
+    Opt { long: "separator", short: None, value: "<s>",
+        help: "What goes between the words, may be empty [default: -]" },
+    Opt { long: "capitalize", short: None, value: "",
+        help: "Starts every word with a capital letter" },
+    Opt { long: "digit", short: None, value: "",
+        help: "Adds a digit to the end of one word" },
+];
 
-    println!("Starting in main()...");
+// Numeric PINs instead of passwords.
+const PIN_OPTIONS: &[Opt] = &[
+    Opt { long: "pin", short: None, value: "<n>",
+        help: "Makes a PIN of 4 to 8 digits, instead of --length" },
+    Opt { long: "skip-common", short: None, value: "",
+        help: "Skips PINs that are easy to guess, like 1234, 0000, years and dates" },
+];
+
+// The options that pick what is made, a password, a passphrase, a template
+// or a PIN, each with the options only it uses. Options of different modes
+// can't be mixed.
+const MODES: &[(&str, &[&str])] = &[
+    ("length", &["alphabet", "rules", "prefix"]),
+    ("words", &["wordlist", "separator", "capitalize", "digit"]),
+    ("template", &[]),
+    ("pin", &["skip-common"]),
+];
+
+// Where the seed comes from. Without any of these it is read from stdin
+// when something is piped in, and from a hidden prompt otherwise.
+const SEED_OPTIONS: &[Opt] = &[
+    Opt { long: "seed-fd", short: None, value: "<fd>",
+        help: "Reads the seed from this open file descriptor" },
+    Opt { long: "seed-file", short: None, value: "<path>",
+        help: "Reads the seed from a file" },
+    Opt { long: "confirm", short: None, value: "",
+        help: "Asks for the seed twice at the prompt, and only works there" },
+    Opt { long: "force-argv-seed", short: None, value: "",
+        help: "Allows secrets as arguments, where shell history and ps can see them" },
+];
+
+// Where verify reads the expected password from. Without either it is
+// asked for at a hidden prompt, since stdin is left to the seed.
+const VERIFY_OPTIONS: &[Opt] = &[
+    Opt { long: "password-fd", short: None, value: "<fd>",
+        help: "Reads the expected password from this open file descriptor" },
+    Opt { long: "password-file", short: None, value: "<path>",
+        help: "Reads the expected password from a file" },
+];
+
+// Where a secret can be read from, other than the hidden prompt.
+struct Secret {
+
+    // What it is called in errors, and at the prompt.
+    name: &'static str,
+    label: &'static str,
+
+    // The positional argument it can be, with --force-argv-seed.
+    index: usize,
+
+    // The options that read it from a file descriptor or a file.
+    fd: &'static str,
+    file: &'static str,
+
+    // Whether it is read from stdin when something is piped in. Only one
+    // secret can be, and it is the one --confirm asks for twice.
+    stdin: bool,
+}
+
+// How much every command writes to stderr.
+const LOG_OPTIONS: &[Opt] = &[
+    Opt { long: "quiet", short: Some('q'), value: "",
+        help: "Only prints errors to stderr" },
+    Opt { long: "verbose", short: Some('v'), value: "",
+        help: "Also prints each step to stderr" },
+    Opt { long: "trace", short: None, value: "",
+        help: "Also prints every thread and chunk to stderr" },
+];
+
+// The options only the site command takes.
+const SITE_OPTIONS: &[Opt] = &[
+    Opt { long: "login", short: None, value: "<name>",
+        help: "The user name on the site [default: none]" },
+    Opt { long: "counter", short: Some('c'), value: "<n>",
+        help: "Raise this for a new password on the same site [default: 1]" },
+];
+
+// The options only the bench command takes.
+const BENCH_OPTIONS: &[Opt] = &[
+    Opt { long: "runs", short: Some('r'), value: "<n>",
+        help: "How many passwords to time [default: 5]" },
+];
+
+const GENERATE: Command = Command {
+    name: "generate",
+    about: "Hashes a seed into a password.",
+    positional: &[("[seed]", "The secret seed, only with --force-argv-seed")],
+    options: &[SEED_OPTIONS, HASH_OPTIONS, PASSPHRASE_OPTIONS, PIN_OPTIONS, LOG_OPTIONS],
+};
+
+const SITE: Command = Command {
+    name: "site",
+    about: "Derives the password for one site from a master secret.",
+    positional: &[("<domain>", "The site, like example.com"),
+        ("[master]", "The master secret, only with --force-argv-seed")],
+    options: &[SITE_OPTIONS, SEED_OPTIONS, HASH_OPTIONS, PASSPHRASE_OPTIONS, PIN_OPTIONS, LOG_OPTIONS],
+};
+
+const BENCH: Command = Command {
+    name: "bench",
+    about: "Times how long a password takes with the given options.",
+    positional: &[],
+    options: &[BENCH_OPTIONS, HASH_OPTIONS, PASSPHRASE_OPTIONS, PIN_OPTIONS, LOG_OPTIONS],
+};
+
+const VERIFY: Command = Command {
+    name: "verify",
+    about: "Checks that a seed still gives the expected password.",
+    positional: &[("[password]", "The password the seed should give, only with --force-argv-seed"),
+        ("[seed]", "The secret seed, only with --force-argv-seed")],
+    options: &[VERIFY_OPTIONS, SEED_OPTIONS, HASH_OPTIONS, PASSPHRASE_OPTIONS, PIN_OPTIONS, LOG_OPTIONS],
+};
+
+const SELFTEST: Command = Command {
+    name: "selftest",
+    about: "Checks every algorithm against its known answers.",
+    positional: &[],
+    options: &[LOG_OPTIONS],
+};
+
+const COMMANDS: [&Command; 5] = [&GENERATE, &SITE, &BENCH, &VERIFY, &SELFTEST];
+
+fn main() {
 
     // Collects command-line arguments
     let args: Vec<String> = env::args().collect();
 
-    println!("Collected arguments.");
-    
-    // Pulls variables out to use them in the program.
-    let seed = args[1].clone();
-    let hashcount = args[2].parse::<u64>().expect("hashcount");
-    let length = args[3].parse::<u64>().expect("length");
-    let threads = args[4].parse::<u64>().expect("threads");
-
-    println!("Distributed arguments.");
-    
-    // Uses the autohash() function.
-    println!("Hashing in main()...");
-    let printme = hashing_arguments::autohash(seed, hashcount, length, threads);
-
-    println!("Done hashing in main().");
-    println!("Your hash is:\n\n{}\n", printme);
+    // Finds the subcommand, or shows the overview.
+    let command = match args.get(1).map(String::as_str) {
+        None => {
+            eprint!("{}", overview());
+            process::exit(USAGE_EXIT_CODE);
+        }
+        Some("help" | "--help" | "-h") => {
+            match args.get(2).and_then(|name| find_command(name)) {
+                Some(command) => print!("{}", command.help()),
+                None => print!("{}", overview()),
+            }
+            return;
+        }
+        Some(name) => find_command(name).unwrap_or_else(|| {
+            eprintln!("Error: '{}' is not a command.\n", name);
+            eprint!("{}", overview());
+            process::exit(USAGE_EXIT_CODE);
+        }),
+    };
+
+    let matches = command.parse(&args[2..]).unwrap_or_else(|e| parse_failed(command, e));
+
+    logger::init(verbosity(command, &matches));
+
+    debug!("Collected arguments.");
+
+    // Runs before any seed is read or hashed.
+    harden::disable_core_dumps();
+    harden::lock_key_buffers();
+
+    match command.name {
+        "generate" => generate(&matches),
+        "site" => site(&matches),
+        "bench" => bench(&matches),
+        "verify" => verify(&matches),
+        _ => selftest(),
+    }
+}
+
+// Hashes a seed into a password.
+fn generate(matches: &Matches) {
+
+    let mut hasher = hasher_for(&GENERATE, matches);
+
+    let seed = read_secret(&GENERATE, matches, &Secret {
+        name: "seed", label: "Seed", index: 0, fd: "seed-fd", file: "seed-file", stdin: true });
+    let _seed_lock = set_seed(&mut hasher, seed);
+
+    // Describes the settings before the hasher is used up.
+    let params = hasher.params();
+
+    debug!("Hashing in main()...");
+    let printme = Zeroizing::new(hasher.run().unwrap_or_else(|e| fail(e)));
+    let _password_lock = MemoryLock::new(printme.as_bytes(), "the password");
+
+    // Only the password goes to stdout.
+    println!("{}", *printme);
+    info!("Regenerate it with: --params '{}'", params);
+}
+
+// Derives the password for one site.
+fn site(matches: &Matches) {
+
+    let domain = matches.positional(0).unwrap_or_default();
+    let login = matches.value("login").unwrap_or_default().to_owned();
+    let counter = matches.number("counter", 1).unwrap_or_else(|e| parse_failed(&SITE, e));
+
+    let mut hasher = hasher_for(&SITE, matches);
+    let params = hasher.params();
+
+    let master = read_secret(&SITE, matches, &Secret {
+        name: "master secret", label: "Master secret", index: 1, fd: "seed-fd", file: "seed-file", stdin: true });
+    let _master_lock = MemoryLock::new(master.as_bytes(), "the master secret");
+
+    let request = SiteRequest::new(master, domain, login, counter);
+
+    // The site seed depends on the length. In prefix mode it is made for a
+    // length of 0, like the hash itself.
+    let length = if params.prefix { 0 } else { params.length };
+    let _seed_lock = set_seed(&mut hasher, std::mem::take(&mut *request.seed(length)));
+
+    debug!("Hashing site {}...", request.site);
+    let printme = Zeroizing::new(hasher.run().unwrap_or_else(|e| fail(e)));
+    let _password_lock = MemoryLock::new(printme.as_bytes(), "the password");
+
+    println!("{}", *printme);
+    info!("Regenerate it with: --params '{}'", params);
+}
+
+// Times passwords for the seed "benchmark".
+fn bench(matches: &Matches) {
+
+    let runs = matches.number("runs", 5).unwrap_or_else(|e| parse_failed(&BENCH, e)).max(1);
+
+    let mut times = Vec::new();
+    let mut params = None;
+
+    for run in 0..runs {
+        let mut hasher = hasher_for(&BENCH, matches);
+        hasher.set_seed(String::from("benchmark"));
+        params.get_or_insert_with(|| hasher.params());
+
+        debug!("Starting run #{}", run);
+
+        let start = Instant::now();
+        hasher.run().unwrap_or_else(|e| fail(e));
+        times.push(start.elapsed());
+    }
+
+    let total: Duration = times.iter().sum();
+    let fastest = times.iter().min().copied().unwrap_or_default();
+
+    println!("Parameters: {}", params.map(|params| params.to_string()).unwrap_or_default());
+    println!("Runs:       {}", runs);
+    println!("Average:    {:.3} ms", total.as_secs_f64() * 1000.0 / runs as f64);
+    println!("Fastest:    {:.3} ms", fastest.as_secs_f64() * 1000.0);
+}
+
+// Regenerates a password and compares it to the expected one.
+fn verify(matches: &Matches) {
+
+    let mut hasher = hasher_for(&VERIFY, matches);
+
+    let expected = Zeroizing::new(read_secret(&VERIFY, matches, &Secret {
+        name: "password", label: "Expected password", index: 0, fd: "password-fd", file: "password-file", stdin: false }));
+    let _expected_lock = MemoryLock::new(expected.as_bytes(), "the expected password");
+
+    let seed = read_secret(&VERIFY, matches, &Secret {
+        name: "seed", label: "Seed", index: 1, fd: "seed-fd", file: "seed-file", stdin: true });
+    let _seed_lock = set_seed(&mut hasher, seed);
+
+    debug!("Hashing in main()...");
+    let printme = Zeroizing::new(hasher.run().unwrap_or_else(|e| fail(e)));
+    let _password_lock = MemoryLock::new(printme.as_bytes(), "the password");
+
+    if printme == expected {
+        println!("The password matches.");
+    } else {
+        println!("The password does not match.");
+        process::exit(MISMATCH_EXIT_CODE);
+    }
+}
+
+// Runs every known answer.
+fn selftest() {
+
+    let failed = algorithm::self_test().err().unwrap_or_default();
+
+    for algorithm in Algorithm::ALL {
+        let result = if failed.contains(&algorithm) { "FAILED" } else { "ok" };
+        println!("{}: {}", algorithm, result);
+    }
+
+    if !failed.is_empty() {
+        process::exit(SELFTEST_EXIT_CODE);
+    }
+}
+
+// Picks the verbosity from the logging switches.
+fn verbosity(command: &Command, matches: &Matches) -> Verbosity {
+
+    if matches.has("quiet") && (matches.has("verbose") || matches.has("trace")) {
+        usage(command, "'--quiet' can't be used with '--verbose' or '--trace'");
+    }
+
+    if matches.has("trace") {
+        Verbosity::Trace
+    } else if matches.has("verbose") {
+        Verbosity::Verbose
+    } else if matches.has("quiet") {
+        Verbosity::Quiet
+    } else {
+        Verbosity::Normal
+    }
+}
+
+// Reads a secret from wherever the options say. Its positional argument
+// is only used when --force-argv-seed is given.
+fn read_secret(command: &Command, matches: &Matches, secret: &Secret) -> String {
+
+    let argument = matches.positional(secret.index);
+
+    // Only one source may be given, so there is never a doubt which was used.
+    let given = [argument.is_some(), matches.has(secret.fd), matches.has(secret.file)];
+    if given.iter().filter(|given| **given).count() > 1 {
+        usage(command, &format!("give the {} only one way", secret.name));
+    }
+
+    // There is nothing to ask twice unless the seed is typed in.
+    const CONFIRM_ONLY: &str = "'--confirm' only works when the seed is asked for at the prompt";
+    let confirm = secret.stdin && matches.has("confirm");
+    if confirm && given.contains(&true) {
+        usage(command, CONFIRM_ONLY);
+    }
+
+    let result = if let Some(value) = argument {
+        if !matches.has("force-argv-seed") {
+            let instead = if secret.stdin {
+                String::from("Pipe it in")
+            } else {
+                format!("Give it with --{} or --{},", secret.fd, secret.file)
+            };
+            usage(command, &format!("arguments leak into shell history, so the {} needs \
+                --force-argv-seed to be one. {} or leave it out to be asked for it", secret.name, instead));
+        }
+        Ok(value.to_owned())
+    } else if matches.has(secret.fd) {
+        let fd = matches.number(secret.fd, 0).unwrap_or_else(|e| parse_failed(command, e));
+        input::from_fd(fd)
+    } else if let Some(path) = matches.value(secret.file) {
+        input::from_file(path)
+    } else if secret.stdin {
+        match input::from_stdin() {
+            Ok(Some(_)) if confirm => usage(command, CONFIRM_ONLY),
+            Ok(Some(value)) => Ok(value),
+            Ok(None) => input::prompt(secret.label, confirm),
+            Err(e) => Err(e),
+        }
+    } else {
+        input::prompt(secret.label, false)
+    };
+
+    let value = result.unwrap_or_else(|e| {
+        eprintln!("Error: the {} could not be read: {}.", secret.name, e);
+        process::exit(SEED_EXIT_CODE);
+    });
+
+    if value.is_empty() {
+        eprintln!("Error: the {} is empty.", secret.name);
+        process::exit(SEED_EXIT_CODE);
+    }
+
+    value
+}
+
+// Gives the hasher its seed, locked in memory until the returned
+// MemoryLock is dropped.
+fn set_seed(hasher: &mut HashArguments, seed: String) -> MemoryLock {
+    let lock = MemoryLock::new(seed.as_bytes(), "the seed");
+    hasher.set_seed(seed);
+    lock
+}
+
+// Builds the HashArguments from the hashing options, with an empty seed.
+// Every option is checked here, so a mistake is found before the seed is
+// typed in rather than after, and set_seed() gives it the seed last.
+fn hasher_for(command: &Command, matches: &Matches) -> HashArguments {
+
+    let threads = matches.number("threads", hashing_arguments::available_threads())
+        .unwrap_or_else(|e| parse_failed(command, e));
+
+    let policy = matches.value("rules")
+        .map(|value| rules::parse(value).unwrap_or_else(|e| fail(e)));
+
+    let wordlist = matches.value("wordlist")
+        .map(|path| Wordlist::load(path).unwrap_or_else(|e| fail(e)));
+
+    // A parameter string replaces every other hashing option.
+    if let Some(value) = matches.value("params") {
+        for long in ["hashcount", "length", "algorithm", "kdf", "alphabet", "template", "prefix",
+            "words", "separator", "capitalize", "digit", "pin", "skip-common"] {
+            if matches.has(long) {
+                usage(command, &format!("'--{}' can't be used with '--params'", long));
+            }
+        }
+
+        let params = value.parse::<Params>().unwrap_or_else(|e| fail(e));
+        return params.hash_arguments(String::new(), threads, policy, wordlist)
+            .unwrap_or_else(|e| fail(e));
+    }
+
+    check_modes(command, matches);
+
+    // Picks what the key is turned into. A passphrase is as long as its
+    // number of words, a template as its shape and a PIN as its digits.
+    let (encoder, length) = if matches.has("words") {
+        (Encoder::Passphrase(passphrase_for(matches, wordlist)), matches.number("words", 0))
+    } else if let Some(id) = matches.value("template") {
+        let template = Template::from_id(id).unwrap_or_else(|e| fail(e));
+        let length = template.len() as u64;
+        (Encoder::Template(template), Ok(length))
+    } else if matches.has("pin") {
+        let mut pin = Pin::new();
+        pin.set_skip_common(matches.has("skip-common"));
+        (Encoder::Pin(pin), matches.number("pin", 0))
+    } else {
+        let alphabet = matches.value("alphabet")
+            .map_or_else(Alphabet::default, |chars| Alphabet::new(chars).unwrap_or_else(|e| fail(e)));
+
+        // Without --length, the default is moved into the range the rules allow.
+        let default_length = policy.as_ref().map_or(DEFAULT_LENGTH, |policy| policy.clamp_length(DEFAULT_LENGTH));
+        let length = matches.number("length", default_length);
+
+        let encoder = match policy {
+            Some(policy) => Encoder::Policy(policy, alphabet),
+            None => Encoder::Alphabet(alphabet),
+        };
+        (encoder, length)
+    };
+
+    let hashcount = matches.number("hashcount", DEFAULT_HASHCOUNT).unwrap_or_else(|e| parse_failed(command, e));
+    let length = length.unwrap_or_else(|e| parse_failed(command, e));
+
+    let algorithm = match matches.value("algorithm") {
+        Some(id) => id.parse::<Algorithm>().unwrap_or_else(|e| fail(e)),
+        None => Algorithm::default(),
+    };
+
+    let mut builder = HashArguments::builder()
+        .seed(String::new())
+        .iterations(hashcount)
+        .length(length)
+        .threads(threads)
+        .algorithm(algorithm)
+        .encoder(encoder)
+        .prefix(matches.has("prefix"));
+
+    if let Some(id) = matches.value("kdf") {
+        builder = builder.kdf(kdf::from_id(id).unwrap_or_else(|e| fail(e)));
+    }
+
+    // Checks every setting before any hashing starts.
+    builder.build().unwrap_or_else(|e| fail(e))
+}
+
+// Checks that only the options of one mode are given, and that the options
+// of a passphrase or a PIN come with the option that asks for one.
+fn check_modes(command: &Command, matches: &Matches) {
+
+    let mut given = MODES.iter()
+        .flat_map(|(mode, options)| std::iter::once(mode).chain(options.iter()).map(move |long| (mode, long)))
+        .filter(|(_, long)| matches.has(long));
+
+    if let Some((mode, first)) = given.next() {
+        if let Some((_, second)) = given.find(|(other, _)| other != &mode) {
+            usage(command, &format!("'--{}' can't be used with '--{}'", second, first));
+        }
+    }
+
+    // A password needs no --length, since it has a default.
+    for (mode, options) in &MODES[1..] {
+        if let Some(long) = options.iter().find(|long| matches.has(long)) {
+            if !matches.has(mode) {
+                usage(command, &format!("'--{}' needs '--{}'", long, mode));
+            }
+        }
+    }
+}
+
+// Reads the passphrase options.
+fn passphrase_for(matches: &Matches, wordlist: Option<Wordlist>) -> Passphrase {
+
+    let wordlist = wordlist.unwrap_or_else(Wordlist::eff_large);
+
+    info!("The wordlist has {} words, {:.2} bits per word", wordlist.len(), wordlist.bits_per_word());
+    let mut passphrase = Passphrase::new(wordlist);
+    if let Some(separator) = matches.value("separator") {
+        passphrase.set_separator(separator);
+    }
+    passphrase.set_capitalize(matches.has("capitalize"));
+    passphrase.set_digit(matches.has("digit"));
+
+    passphrase
+}
+
+// Finds a subcommand by name.
+fn find_command(name: &str) -> Option<&'static Command> {
+    COMMANDS.into_iter().find(|command| command.name == name)
+}
+
+// Lists every subcommand.
+fn overview() -> String {
+
+    let mut text = String::from("Turns a secret seed into a password.\n\n");
+    text.push_str("Usage: neo_passgen <command> [options]\n\nCommands:\n");
+
+    for command in COMMANDS {
+        text.push_str(&format!("  {:<10} {}\n", command.name, command.about));
+    }
+
+    text.push_str("\nRun 'neo_passgen <command> --help' for the options of a command.\n");
+    text
+}
+
+// Exits after the arguments could not be parsed, or prints --help.
+fn parse_failed(command: &Command, error: ParseError) -> ! {
+    match error {
+        ParseError::Help => {
+            print!("{}", command.help());
+            process::exit(0);
+        }
+        ParseError::Usage(reason) => usage(command, &reason),
+    }
+}
+
+// Prints what was wrong with the arguments and exits.
+fn usage(command: &Command, reason: &str) -> ! {
+    eprintln!("Error: {}.", reason);
+    eprintln!("Run 'neo_passgen {} --help' for usage.", command.name);
+    process::exit(USAGE_EXIT_CODE);
+}
+
+// Prints the error and exits with its code.
+fn fail(error: PassgenError) -> ! {
+    eprintln!("Error: {}.", error);
+    process::exit(exit_code(&error));
+}
+
+// Gives each kind of error its own exit code so scripts can tell them apart.
+fn exit_code(error: &PassgenError) -> i32 {
+    match error {
+        PassgenError::InvalidThreadCount(_) => 3,
+        PassgenError::ZeroLength => 4,
+        PassgenError::Overflow(_) => 5,
+        PassgenError::ThreadFailure => 6,
+        PassgenError::EncodingFailure => 7,
+        PassgenError::UnknownAlgorithm(_) => 8,
+        PassgenError::InvalidKdfParameters(_) => 9,
+        PassgenError::InvalidAlphabet(_) => 10,
+        PassgenError::InvalidPolicy(_) => 11,
+        PassgenError::InvalidParameters(_) => 12,
+        PassgenError::MissingSetting(_) => 16,
+        PassgenError::UnsupportedPrefix(_) => 17,
+        PassgenError::InvalidPassphrase(_) => 18,
+        PassgenError::InvalidTemplate(_) => 19,
+        PassgenError::InvalidPin(_) => 20,
+    }
 }
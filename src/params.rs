@@ -0,0 +1,543 @@
+// Self-describing parameter strings, so a password can be regenerated
+// exactly from the seed and one line of text, for example
+// "npg3$argon2id$m=65536,t=3,p=1$n=1,len=24".
+//
+// The parts are the algorithm, the kdf id (left out by versions without
+// one), and the settings: n is the hashcount, len the length, a the hex
+// of a custom alphabet, p the fingerprint of a policy and prefix=1 marks
+// prefix mode. A passphrase is marked by w, the fingerprint of its
+// wordlist, with s the hex of a separator other than "-", c=1 for capitals
+// and d=1 for a digit. In passphrase mode len is the number of words. t is
+// a template preset or a custom template. pin=1 makes a PIN of len digits,
+// and b=1 skips the common ones. A password, a passphrase, a template and a
+// PIN can't be mixed.
+
+use crate::error::PassgenError;
+
+use crate::algorithm::{Algorithm, Encoder};
+
+use crate::alphabet::Alphabet;
+
+use crate::policy::Policy;
+
+use crate::passphrase::{Passphrase, DEFAULT_SEPARATOR};
+
+use crate::wordlist::Wordlist;
+
+use crate::template::Template;
+
+use crate::pin::Pin;
+
+use crate::kdf;
+
+use crate::hashing_arguments::HashArguments;
+
+use std::fmt;
+use std::str::FromStr;
+
+// Everything besides the seed that changes a password.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Params {
+    pub algorithm: Algorithm,
+    pub kdf: Option<String>,
+    pub hashcount: u64,
+    pub length: u64,
+    pub alphabet: Option<String>,
+    pub policy: Option<String>,
+    pub prefix: bool,
+    pub wordlist: Option<String>,
+    pub separator: Option<String>,
+    pub capitalize: bool,
+    pub digit: bool,
+    pub template: Option<String>,
+    pub pin: bool,
+    pub skip_common: bool
+}
+
+impl Params {
+
+    // Builds the HashArguments these parameters describe. The policy and a
+    // loaded wordlist can't be stored in the String, so they are passed in
+    // and checked against their fingerprints.
+    pub fn hash_arguments(
+        &self,
+        seed: String,
+        threads: u64,
+        policy: Option<Policy>,
+        wordlist: Option<Wordlist>) -> Result<HashArguments, PassgenError> {
+
+        let mut builder = HashArguments::builder()
+            .seed(seed)
+            .iterations(self.hashcount)
+            .length(self.length)
+            .threads(threads)
+            .algorithm(self.algorithm)
+            .prefix(self.prefix);
+
+        if let Some(id) = &self.kdf {
+            builder = builder.kdf(kdf::from_id(id)?);
+        }
+
+        self.check_modes()?;
+
+        // Refuses to regenerate with a different policy, or with none.
+        let fingerprint = policy.as_ref().map(Policy::fingerprint);
+        if fingerprint != self.policy {
+            return Err(PassgenError::InvalidParameters(
+                String::from("the policy does not match the one the parameters were made with")));
+        }
+
+        // Refuses to regenerate with a different wordlist. The bundled one
+        // is found by its fingerprint.
+        let wordlist = match (&self.wordlist, wordlist) {
+            (None, None) => None,
+            (Some(id), Some(wordlist)) if *id == wordlist.fingerprint() => Some(wordlist),
+            (Some(id), None) if *id == Wordlist::eff_large().fingerprint() => Some(Wordlist::eff_large()),
+            (Some(id), None) => return Err(PassgenError::InvalidParameters(
+                format!("this passphrase needs --wordlist <file> with fingerprint {}", id))),
+            _ => return Err(PassgenError::InvalidParameters(
+                String::from("the wordlist does not match the one the parameters were made with"))),
+        };
+
+        let alphabet = match &self.alphabet {
+            Some(chars) => Alphabet::new(chars)?,
+            None => Alphabet::default(),
+        };
+
+        let encoder = if let Some(wordlist) = wordlist {
+            let mut passphrase = Passphrase::new(wordlist);
+            passphrase.set_separator(self.separator.as_deref().unwrap_or(DEFAULT_SEPARATOR));
+            passphrase.set_capitalize(self.capitalize);
+            passphrase.set_digit(self.digit);
+            Encoder::Passphrase(passphrase)
+        } else if let Some(id) = &self.template {
+            Encoder::Template(Template::from_id(id)?)
+        } else if self.pin {
+            let mut pin = Pin::new();
+            pin.set_skip_common(self.skip_common);
+            Encoder::Pin(pin)
+        } else if let Some(policy) = policy {
+            Encoder::Policy(policy, alphabet)
+        } else {
+            Encoder::Alphabet(alphabet)
+        };
+
+        builder.encoder(encoder).build()
+    }
+
+    // Checks that at most one of a custom password, a passphrase, a
+    // template and a PIN is asked for.
+    fn check_modes(&self) -> Result<(), PassgenError> {
+
+        let modes = [
+            (self.alphabet.is_some() || self.policy.is_some(), "a or p"),
+            (self.wordlist.is_some(), "w"),
+            (self.template.is_some(), "t"),
+            (self.pin, "pin"),
+        ];
+
+        match modes.iter().filter(|(given, _)| *given).map(|(_, keys)| *keys).collect::<Vec<_>>()[..] {
+            [first, second, ..] => Err(PassgenError::InvalidParameters(
+                format!("{} can't be used with {}", second, first))),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        write!(f, "{}$", self.algorithm)?;
+
+        if let Some(id) = &self.kdf {
+            write!(f, "{}$", id)?;
+        }
+
+        write!(f, "n={},len={}", self.hashcount, self.length)?;
+
+        if let Some(chars) = &self.alphabet {
+            write!(f, ",a={}", to_hex(chars))?;
+        }
+
+        if let Some(fingerprint) = &self.policy {
+            write!(f, ",p={}", fingerprint)?;
+        }
+
+        if self.prefix {
+            f.write_str(",prefix=1")?;
+        }
+
+        if let Some(id) = &self.wordlist {
+            write!(f, ",w={}", id)?;
+        }
+        if let Some(separator) = &self.separator {
+            write!(f, ",s={}", to_hex(separator))?;
+        }
+        if self.capitalize {
+            f.write_str(",c=1")?;
+        }
+        if self.digit {
+            f.write_str(",d=1")?;
+        }
+
+        if let Some(id) = &self.template {
+            write!(f, ",t={}", id)?;
+        }
+
+        if self.pin {
+            f.write_str(",pin=1")?;
+        }
+        if self.skip_common {
+            f.write_str(",b=1")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Params {
+    type Err = PassgenError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+
+        let bad = |reason: &str| PassgenError::InvalidParameters(reason.to_owned());
+
+        // The algorithm comes first and the settings last. Anything between is the kdf.
+        let (algorithm, rest) = input.split_once('$').ok_or_else(|| bad("there are no settings"))?;
+        let (kdf_id, settings) = match rest.rsplit_once('$') {
+            Some((kdf_id, settings)) => (Some(kdf_id), settings),
+            None => (None, rest),
+        };
+
+        let algorithm = algorithm.parse::<Algorithm>()?;
+
+        // Only versions with a kdf may name one, and they must.
+        let kdf = match (algorithm.uses_kdf(), kdf_id) {
+            (true, Some(id)) => Some(kdf::from_id(id)?.id()),
+            (false, None) => None,
+            (true, None) => return Err(bad("the kdf is missing")),
+            (false, Some(_)) => return Err(bad("this algorithm does not use a kdf")),
+        };
+
+        let mut hashcount = None;
+        let mut length = None;
+        let mut alphabet = None;
+        let mut policy = None;
+        let mut prefix = false;
+        let mut wordlist = None;
+        let mut separator = None;
+        let mut capitalize = false;
+        let mut digit = false;
+        let mut template = None;
+        let mut pin = false;
+        let mut skip_common = false;
+
+        // Each setting may only be given once, so no two strings mean the same.
+        let mut keys = Vec::new();
+
+        for pair in settings.split(',') {
+            let (key, value) = pair.split_once('=').ok_or_else(|| bad(pair))?;
+
+            if keys.contains(&key) {
+                return Err(bad(&format!("{} is given twice", key)));
+            }
+            keys.push(key);
+
+            match key {
+                "n" => hashcount = Some(value.parse::<u64>().map_err(|_| bad(pair))?),
+                "len" => length = Some(value.parse::<u64>().map_err(|_| bad(pair))?),
+                "a" => {
+                    let chars = from_hex(value).ok_or_else(|| bad(pair))?;
+
+                    // Checks the alphabet now rather than at hashing time.
+                    Alphabet::new(&chars)?;
+                    alphabet = Some(chars);
+                }
+                "p" => policy = Some(value.to_owned()),
+                "prefix" if value == "1" => prefix = true,
+                "w" if is_fingerprint(value) => wordlist = Some(value.to_owned()),
+                "s" => separator = Some(from_hex(value).ok_or_else(|| bad(pair))?),
+                "c" if value == "1" => capitalize = true,
+                "d" if value == "1" => digit = true,
+                "t" => {
+
+                    // Checks the template now rather than at hashing time.
+                    Template::from_id(value)?;
+                    template = Some(value.to_owned());
+                }
+                "pin" if value == "1" => pin = true,
+                "b" if value == "1" => skip_common = true,
+                _ => return Err(bad(pair)),
+            }
+        }
+
+        // The other passphrase settings mean nothing without a wordlist.
+        if wordlist.is_none() && (separator.is_some() || capitalize || digit) {
+            return Err(bad("w is missing"));
+        }
+        if skip_common && !pin {
+            return Err(bad("pin is missing"));
+        }
+
+        let params = Self {
+            algorithm,
+            kdf,
+            hashcount: hashcount.ok_or_else(|| bad("n is missing"))?,
+            length: length.ok_or_else(|| bad("len is missing"))?,
+            alphabet,
+            policy,
+            prefix,
+            wordlist,
+            separator,
+            capitalize,
+            digit,
+            template,
+            pin,
+            skip_common
+        };
+
+        params.check_modes()?;
+
+        Ok(params)
+    }
+}
+
+// Writes text as lowercase hex, so it can't be mistaken for the punctuation around it.
+fn to_hex(text: &str) -> String {
+    text.bytes().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Whether an id looks like the fingerprint of a loaded wordlist.
+fn is_fingerprint(id: &str) -> bool {
+    id.len() == 16 && id.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+// Turns hex back into text.
+fn from_hex(hex: &str) -> Option<String> {
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|index| hex.get(index..index + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::policy::CharClass;
+
+    // Runs the arguments through every step.
+    fn finish(args: HashArguments) -> String {
+        args.run().unwrap()
+    }
+
+    // Tests the format of a parameter string.
+    #[test]
+    fn params_format() {
+
+        let mut args = HashArguments::new(String::from("apple"), 1, 24, 1).unwrap();
+        args.set_algorithm(Algorithm::V5);
+        args.set_kdf(kdf::from_id("argon2id").unwrap());
+        args.set_alphabet(Alphabet::new("ab$,").unwrap());
+
+        assert_eq!(args.params().to_string(), "npg5$argon2id$m=65536,t=3,p=1$n=1,len=24,a=6162242c");
+
+        let mut legacy = HashArguments::new(String::from("apple"), 5, 32, 1).unwrap();
+        legacy.set_algorithm(Algorithm::V1);
+
+        assert_eq!(legacy.params().to_string(), "npg1$n=5,len=32");
+    }
+
+    // Tests that a printed parameter string regenerates the same password.
+    #[test]
+    fn params_round_trip() {
+
+        let mut policy = Policy::new();
+        policy.require(CharClass::Digit, 2);
+
+        let mut args = HashArguments::new(String::from("apple"), 10, 30, 1).unwrap();
+        args.set_kdf(kdf::from_id("scrypt$ln=4,r=8,p=1").unwrap());
+        args.set_alphabet(Alphabet::new("abcdefgh0123456789").unwrap());
+        args.set_policy(policy.clone());
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        assert_eq!(parsed, params);
+
+        let regenerated = parsed.hash_arguments(String::from("apple"), 1, Some(policy), None).unwrap();
+        assert_eq!(finish(regenerated), comparitor);
+    }
+
+    // Tests that prefix mode is kept in the String and regenerates the same password.
+    #[test]
+    fn params_prefix() {
+
+        let mut args = HashArguments::new(String::from("apple"), 10, 20, 1).unwrap();
+        args.set_algorithm(Algorithm::V5);
+        args.set_prefix(true);
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        assert_eq!(params.to_string(), "npg5$sha512$n=10,len=20,prefix=1");
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        assert_eq!(finish(parsed.hash_arguments(String::from("apple"), 1, None, None).unwrap()), comparitor);
+
+        // npg4 can't make prefix passwords.
+        let legacy = "npg4$sha512$n=10,len=20,prefix=1".parse::<Params>().unwrap();
+        assert!(matches!(legacy.hash_arguments(String::from("apple"), 1, None, None),
+            Err(PassgenError::UnsupportedPrefix(_))));
+    }
+
+    // Tests that a passphrase is kept in the String and regenerates the same words.
+    #[test]
+    fn params_passphrase() {
+
+        let wordlist = Wordlist::parse("alpha\nbravo\ncharlie\ndelta").unwrap();
+        let mut passphrase = Passphrase::new(wordlist.clone());
+        passphrase.set_separator(" ");
+        passphrase.set_digit(true);
+
+        let mut args = HashArguments::new(String::from("apple"), 10, 6, 1).unwrap();
+        args.set_algorithm(Algorithm::V5);
+        args.set_encoder(Encoder::Passphrase(passphrase));
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        assert_eq!(params.to_string(), format!("npg5$sha512$n=10,len=6,w={},s=20,d=1", wordlist.fingerprint()));
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        assert_eq!(parsed, params);
+        assert_eq!(finish(parsed.hash_arguments(String::from("apple"), 1, None, Some(wordlist)).unwrap()), comparitor);
+    }
+
+    // Tests that a passphrase from the bundled list regenerates without a file.
+    #[test]
+    fn params_bundled_wordlist() {
+
+        let mut args = HashArguments::new(String::from("apple"), 10, 6, 1).unwrap();
+        args.set_algorithm(Algorithm::V5);
+        args.set_encoder(Encoder::Passphrase(Passphrase::new(Wordlist::eff_large())));
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        assert_eq!(params.to_string(), "npg5$sha512$n=10,len=6,w=55e72319c9479de4");
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        assert_eq!(finish(parsed.hash_arguments(String::from("apple"), 1, None, None).unwrap()), comparitor);
+    }
+
+    // Tests that a template is kept in the String and regenerates the same password.
+    #[test]
+    fn params_template() {
+
+        for id in ["long", "Cvcvnocvcv"] {
+            let template = Template::from_id(id).unwrap();
+            let args = HashArguments::builder().seed(String::from("apple")).iterations(10)
+                .algorithm(Algorithm::V5).encoder(Encoder::Template(template)).build().unwrap();
+
+            let params = args.params();
+            let comparitor = finish(args);
+
+            assert_eq!(params.to_string(), format!("npg5$sha512$n=10,len={},t={}", comparitor.len(), id));
+
+            let parsed = params.to_string().parse::<Params>().unwrap();
+            assert_eq!(finish(parsed.hash_arguments(String::from("apple"), 1, None, None).unwrap()), comparitor);
+        }
+
+        // The length has to be the template's.
+        let wrong = "npg5$sha512$n=10,len=12,t=long".parse::<Params>().unwrap();
+        assert!(matches!(wrong.hash_arguments(String::from("apple"), 1, None, None),
+            Err(PassgenError::InvalidTemplate(_))));
+    }
+
+    // Tests that a PIN is kept in the String and regenerates the same digits.
+    #[test]
+    fn params_pin() {
+
+        let mut pin = Pin::new();
+        pin.set_skip_common(true);
+
+        let args = HashArguments::builder().seed(String::from("apple")).iterations(10).length(6)
+            .algorithm(Algorithm::V5).encoder(Encoder::Pin(pin)).build().unwrap();
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        assert_eq!(params.to_string(), "npg5$sha512$n=10,len=6,pin=1,b=1");
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        assert_eq!(parsed, params);
+        assert_eq!(finish(parsed.hash_arguments(String::from("apple"), 1, None, None).unwrap()), comparitor);
+    }
+
+    // Tests that a loaded wordlist is named by its fingerprint and has to match.
+    #[test]
+    fn params_wordlist_mismatch() {
+
+        let wordlist = Wordlist::parse("alpha\nbravo\ncharlie\ndelta").unwrap();
+        let passphrase = Passphrase::new(wordlist.clone());
+
+        let mut args = HashArguments::new(String::from("apple"), 10, 4, 1).unwrap();
+        args.set_algorithm(Algorithm::V5);
+        args.set_encoder(Encoder::Passphrase(passphrase));
+
+        let params = args.params();
+        let comparitor = finish(args);
+
+        assert_eq!(params.wordlist.as_deref(), Some(wordlist.fingerprint()));
+
+        let parsed = params.to_string().parse::<Params>().unwrap();
+        let regenerated = parsed.hash_arguments(String::from("apple"), 1, None, Some(wordlist.clone())).unwrap();
+        assert_eq!(finish(regenerated), comparitor);
+
+        // Without the list, the error names the list that is needed.
+        let missing = parsed.hash_arguments(String::from("apple"), 1, None, None).unwrap_err();
+        assert_eq!(missing, PassgenError::InvalidParameters(
+            format!("this passphrase needs --wordlist <file> with fingerprint {}", wordlist.fingerprint())));
+
+        // A changed list or a list that was never asked for are refused.
+        let changed = Wordlist::parse("alpha\nbravo\ncharlie\necho").unwrap();
+        for (params, wordlist) in [(&parsed, Some(changed.clone())),
+            (&"npg5$sha512$n=10,len=4,w=0123456789abcdef".parse::<Params>().unwrap(), Some(changed.clone())),
+            (&"npg5$sha512$n=10,len=4".parse::<Params>().unwrap(), Some(changed))] {
+            assert!(matches!(params.hash_arguments(String::from("apple"), 1, None, wordlist),
+                Err(PassgenError::InvalidParameters(_))));
+        }
+    }
+
+    // Tests that a different policy is refused.
+    #[test]
+    fn params_policy_mismatch() {
+
+        let mut policy = Policy::new();
+        policy.require(CharClass::Digit, 2);
+
+        let params = "npg3$sha512$n=1,len=30".parse::<Params>().unwrap();
+
+        assert!(matches!(params.hash_arguments(String::from("apple"), 1, Some(policy), None),
+            Err(PassgenError::InvalidParameters(_))));
+    }
+
+    // Tests that malformed parameter strings are errors.
+    #[test]
+    fn params_rejects_bad_input() {
+        for input in ["npg3", "npg3$n=1,len=2", "npg1$sha512$n=1,len=2", "npg3$sha512$n=1",
+            "npg3$sha512$n=x,len=2", "npg3$sha512$n=1,len=2,q=3", "npg3$sha512$n=1,len=2,a=zz",
+            "npg5$sha512$n=1,len=2,prefix=0", "npg5$sha512$n=1,len=2,w=bip39",
+            "npg5$sha512$n=1,len=2,c=1", "npg5$sha512$n=1,len=2,w=0123456789abcdef,s=2", "npg5$sha512$n=1,len=2,t=Cx?",
+            "npg5$sha512$n=1,len=4,b=1", "npg5$sha512$n=1,len=4,pin=0", "npg5$sha512$n=1,len=4,t=nnnn,pin=1",
+            "npg5$sha512$n=1,len=4,a=6162,pin=1", "npg5$sha512$n=1,len=4,pin=1,pin=1", "npg3$sha512$n=1,n=1,len=2", "npg5$sha512$n=1,len=4,p=0123456789abcdef,t=nnnn"] {
+            assert!(input.parse::<Params>().is_err(), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// Diceware style passphrases, like "cargo-limit-staple-overhaul-bunny-gulf".
+//
+// Every word is picked from the wordlist with the same rejection sampling
+// as the characters of a password, so each word is exactly as likely as
+// every other one. In passphrase mode the length is the number of words.
+
+use crate::alphabet::ByteStream;
+
+use crate::wordlist::Wordlist;
+
+// Shares a loaded list between copies of a passphrase.
+use std::sync::Arc;
+
+// The separator used unless another one is set.
+pub const DEFAULT_SEPARATOR: &str = "-";
+
+// The most words a passphrase can have. Words from a loaded list can be
+// long, so this is far below the longest password.
+pub const MAX_WORDS: u64 = 1 << 12;
+
+// How a passphrase is put together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Passphrase {
+    wordlist: Arc<Wordlist>,
+    separator: String,
+    capitalize: bool,
+    digit: bool
+}
+
+impl Passphrase {
+
+    // Words from the list as they are, joined by hyphens.
+    pub fn new(wordlist: Wordlist) -> Self {
+        Self {
+            wordlist: Arc::new(wordlist),
+            separator: String::from(DEFAULT_SEPARATOR),
+            capitalize: false,
+            digit: false
+        }
+    }
+
+    // Picks the words the passphrase is made of.
+    pub fn set_wordlist(&mut self, wordlist: Wordlist) {
+
+        // Sets the wordlist value.
+        self.wordlist = Arc::new(wordlist);
+    }
+
+    // Puts `separator` between the words. It may be empty.
+    pub fn set_separator(&mut self, separator: &str) {
+
+        // Sets the separator value.
+        self.separator = separator.to_owned();
+    }
+
+    // Starts every word with a capital letter.
+    pub fn set_capitalize(&mut self, capitalize: bool) {
+
+        // Sets the capitalize value.
+        self.capitalize = capitalize;
+    }
+
+    // Adds one digit to the end of one word, both picked from the key.
+    pub fn set_digit(&mut self, digit: bool) {
+
+        // Sets the digit value.
+        self.digit = digit;
+    }
+
+    // The words the passphrase is made of.
+    pub fn wordlist(&self) -> &Wordlist {
+        &self.wordlist
+    }
+
+    // What goes between the words.
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    // Whether every word starts with a capital letter.
+    pub fn capitalize(&self) -> bool {
+        self.capitalize
+    }
+
+    // Whether one word ends with a digit.
+    pub fn digit(&self) -> bool {
+        self.digit
+    }
+
+    // Draws `words` words from the stream and joins them.
+    pub(crate) fn generate(&self, stream: &mut ByteStream, words: usize) -> String {
+
+        let list = self.wordlist.words();
+
+        // The words are picked first, then where the digit goes and which it is.
+        let picks: Vec<&str> = (0..words).map(|_| list[stream.uniform(list.len())].as_str()).collect();
+        let digit = (self.digit && words > 0)
+            .then(|| (stream.uniform(words), stream.uniform(10)));
+
+        // Leaves room for everything, so the passphrase is never copied as it grows.
+        let capacity = picks.iter().map(|word| word.len()).sum::<usize>()
+            + self.separator.len() * words.saturating_sub(1)
+            + usize::from(digit.is_some());
+        let mut passphrase = String::with_capacity(capacity);
+
+        for (index, word) in picks.into_iter().enumerate() {
+            if index > 0 {
+                passphrase.push_str(&self.separator);
+            }
+
+            let mut chars = word.chars();
+            if let (true, Some(first)) = (self.capitalize, chars.next()) {
+                passphrase.extend(first.to_uppercase());
+                passphrase.push_str(chars.as_str());
+            } else {
+                passphrase.push_str(word);
+            }
+
+            if let Some((_, value)) = digit.filter(|(position, _)| *position == index) {
+                passphrase.push(char::from(b'0' + value as u8));
+            }
+        }
+
+        passphrase
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that the words come from the list in the order the key picks them.
+    #[test]
+    fn generate_picks_words() {
+
+        let words: Vec<String> = ["apple", "banana", "cherry", "damson"].map(String::from).to_vec();
+        let mut passphrase = Passphrase::new(Wordlist::new(words));
+
+        // Four words need one byte each, so the key is used as it is.
+        let key = [0, 5, 2, 255, 3];
+        assert_eq!(passphrase.generate(&mut ByteStream::new(&key), 5), "apple-banana-cherry-damson-damson");
+
+        passphrase.set_separator(" ");
+        passphrase.set_capitalize(true);
+        assert_eq!(passphrase.generate(&mut ByteStream::new(&key), 2), "Apple Banana");
+
+        // The digit goes after the second word and is a 7.
+        passphrase.set_separator("");
+        passphrase.set_digit(true);
+        assert_eq!(passphrase.generate(&mut ByteStream::new(&[0, 1, 1, 7]), 2), "AppleBanana7");
+    }
+
+    // Tests that every word of a list the size of the EFF one shows up about
+    // as often as every other one.
+    #[test]
+    fn generate_is_uniform() {
+
+        let words: Vec<String> = (0..7776).map(|index| format!("word{:04}", index)).collect();
+        let mut passphrase = Passphrase::new(Wordlist::new(words));
+        passphrase.set_separator(" ");
+
+        let mut stream = ByteStream::new(b"apple");
+        let output = passphrase.generate(&mut stream, 777_600);
+
+        let mut counts = std::collections::HashMap::new();
+        for word in output.split(' ') {
+            *counts.entry(word).or_insert(0u32) += 1;
+        }
+
+        // Each word is expected 100 times.
+        assert_eq!(counts.len(), 7776);
+        assert!(counts.values().all(|count| (40..170).contains(count)));
+    }
+}
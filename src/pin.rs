@@ -0,0 +1,168 @@
+// Numeric PINs for phones and bank cards.
+//
+// The whole PIN is drawn as one number below 10^length with the same
+// rejection sampling as every other character, so each PIN is exactly as
+// likely as every other one. PINs that people guess first can be skipped:
+// the next number from the key is drawn instead, so the same inputs always
+// skip to the same PIN.
+
+use crate::alphabet::ByteStream;
+
+// The shortest PIN that can be made.
+pub const MIN_LENGTH: u64 = 4;
+
+// The longest PIN that can be made. 10^8 still fits the 4 bytes a draw uses.
+pub const MAX_LENGTH: u64 = 8;
+
+// How a PIN is made.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Pin {
+    skip_common: bool
+}
+
+impl Pin {
+
+    // Any PIN, even 1234.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Skips the PINs is_common() finds.
+    pub fn set_skip_common(&mut self, skip_common: bool) {
+
+        // Sets the skip_common value.
+        self.skip_common = skip_common;
+    }
+
+    // Whether common PINs are skipped.
+    pub fn skip_common(&self) -> bool {
+        self.skip_common
+    }
+
+    // Whether a PIN is one people try first: one digit or group of digits
+    // repeated like 0000 or 1212, a run like 1234 or 9876, a year from
+    // 1900 to 2099, or a date like 2512, 251287 or 19871225.
+    pub fn is_common(pin: &str) -> bool {
+
+        let digits = pin.as_bytes();
+        let length = digits.len();
+
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return false;
+        }
+
+        let repeated = (1..length)
+            .filter(|period| length.is_multiple_of(*period))
+            .any(|period| digits.iter().zip(&digits[period..]).all(|(a, b)| a == b));
+
+        let run = [1, -1].iter().any(|step| digits.windows(2)
+            .all(|pair| i16::from(pair[1]) - i16::from(pair[0]) == *step));
+
+        repeated || run || is_date(pin)
+    }
+
+    // Draws a PIN of `length` digits from the stream.
+    pub(crate) fn generate(&self, stream: &mut ByteStream, length: usize) -> String {
+
+        let bound = 10usize.pow(length as u32);
+
+        loop {
+            let pin = format!("{:0length$}", stream.uniform(bound));
+
+            if !(self.skip_common && Self::is_common(&pin)) {
+                return pin;
+            }
+        }
+    }
+}
+
+// Whether the PIN reads as a year, or a day and month with or without a year.
+fn is_date(pin: &str) -> bool {
+
+    let part = |range: std::ops::Range<usize>| pin[range].parse::<u32>().unwrap_or(0);
+    let year = |range| (1900..=2099).contains(&part(range));
+
+    // Day then month, or month then day.
+    let day_month = |day, month| is_day_of(part(day), part(month));
+    let either = |first: std::ops::Range<usize>, second: std::ops::Range<usize>|
+        day_month(first.clone(), second.clone()) || day_month(second, first);
+
+    match pin.len() {
+        4 => year(0..4) || either(0..2, 2..4),
+        6 => either(0..2, 2..4) || day_month(4..6, 2..4),
+        8 => (either(0..2, 2..4) && year(4..8)) || (year(0..4) && day_month(6..8, 4..6)),
+        _ => false,
+    }
+}
+
+// Whether the day is in the month, counting February 29.
+fn is_day_of(day: u32, month: u32) -> bool {
+
+    let days = match month {
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => 0,
+    };
+
+    (1..=days).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that guessable PINs are found and others are not.
+    #[test]
+    fn is_common_finds_guessable_pins() {
+
+        for pin in ["1234", "0000", "1212", "9876", "2512", "1225", "1987", "2024", "3112",
+            "123123", "251287", "871225", "25121987", "19871225", "45678", "1111111"] {
+            assert!(Pin::is_common(pin), "{}", pin);
+        }
+
+        for pin in ["7391", "0042", "8264", "3002", "3231", "583920", "58213740", "13579"] {
+            assert!(!Pin::is_common(pin), "{}", pin);
+        }
+    }
+
+    // Tests that a common PIN is skipped for the next one from the key.
+    #[test]
+    fn generate_skips_common() {
+
+        // Two bytes make each draw, and 0x04d2 is 1234.
+        let key = [0x04, 0xd2, 0x00, 0x2a];
+        let mut pin = Pin::new();
+
+        assert_eq!(pin.generate(&mut ByteStream::new(&key), 4), "1234");
+
+        pin.set_skip_common(true);
+        assert_eq!(pin.generate(&mut ByteStream::new(&key), 4), "0042");
+
+        let mut stream = ByteStream::new(b"apple");
+        for length in MIN_LENGTH..=MAX_LENGTH {
+            for _ in 0..200 {
+                let output = pin.generate(&mut stream, length as usize);
+
+                assert_eq!(output.len(), length as usize);
+                assert!(!Pin::is_common(&output), "{}", output);
+            }
+        }
+    }
+
+    // Tests that every first digit shows up about as often as every other one.
+    #[test]
+    fn generate_is_uniform() {
+
+        let pin = Pin::new();
+        let mut stream = ByteStream::new(b"apple");
+
+        let mut counts = [0u32; 10];
+        for _ in 0..20000 {
+            counts[usize::from(pin.generate(&mut stream, 8).as_bytes()[0] - b'0')] += 1;
+        }
+
+        // Each digit is expected 2000 times.
+        assert!(counts.iter().all(|count| (1800..2200).contains(count)), "{:?}", counts);
+    }
+}
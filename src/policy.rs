@@ -0,0 +1,556 @@
+// Password rules such as "at least one digit, no more than 2 repeats in a row".
+//
+// The required characters are picked first, the rest of the password is
+// filled from every allowed character, and then the whole thing is shuffled.
+// If that leaves a run longer than the limit, the same required characters
+// are laid out again with the fillers, one place at a time, so the limit is
+// met without drawing whole passwords over and over. All of it is drawn
+// from the hash, so the same inputs always give the same compliant password.
+
+use crate::error::PassgenError;
+
+use crate::alphabet::{Alphabet, ByteStream};
+
+// SHA512 hashes binary data
+use sha2::{Sha512, Digest};
+
+// Wipes the candidate password and its characters once it is built.
+use zeroize::Zeroizing;
+
+// A group of characters a policy can allow or require.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CharClass {
+
+    // 'a' through 'z'.
+    Lower,
+
+    // 'A' through 'Z'.
+    Upper,
+
+    // '0' through '9'.
+    Digit,
+
+    // The printable ASCII characters that are not letters or digits.
+    Symbol,
+
+    // Any other set of characters.
+    Custom(Vec<char>),
+}
+
+impl CharClass {
+
+    // Every character in the class.
+    pub fn chars(&self) -> Vec<char> {
+        match self {
+            Self::Lower => ('a'..='z').collect(),
+            Self::Upper => ('A'..='Z').collect(),
+            Self::Digit => ('0'..='9').collect(),
+            Self::Symbol => ('!'..='~').filter(|c| !c.is_ascii_alphanumeric()).collect(),
+            Self::Custom(chars) => chars.clone(),
+        }
+    }
+}
+
+// The rules a password has to follow.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Policy {
+    allowed: Vec<CharClass>,
+    required: Vec<(CharClass, usize)>,
+    forbidden: Vec<char>,
+    max_consecutive: Option<usize>,
+    min_length: Option<u64>,
+    max_length: Option<u64>
+}
+
+impl Policy {
+
+    // A policy with no rules. Every character of the alphabet is allowed.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Allows a class of characters. Once any class is allowed, only allowed
+    // and required classes are used instead of the whole alphabet.
+    pub fn allow(&mut self, class: CharClass) {
+        self.allowed.push(class);
+    }
+
+    // Requires at least `min` characters from the class.
+    pub fn require(&mut self, class: CharClass, min: usize) {
+        self.required.push((class, min));
+    }
+
+    // Never uses any of these characters.
+    pub fn forbid(&mut self, chars: &str) {
+        self.forbidden.extend(chars.chars());
+    }
+
+    // Sets the longest run of one repeated character. No password can be
+    // made without one character in a row, so 0 is refused.
+    pub fn set_max_consecutive(&mut self, max: usize) -> Result<(), PassgenError> {
+
+        if max < 1 {
+            return Err(PassgenError::InvalidPolicy(String::from("max-consecutive must be at least 1")));
+        }
+
+        // Sets the max_consecutive value.
+        self.max_consecutive = Some(max);
+
+        Ok(())
+    }
+
+    // Sets the shortest password the policy accepts.
+    pub fn set_min_length(&mut self, min: u64) {
+        self.min_length = Some(min);
+    }
+
+    // Sets the longest password the policy accepts.
+    pub fn set_max_length(&mut self, max: u64) {
+        self.max_length = Some(max);
+    }
+
+    // A short hash of every rule, in order, for parameter strings.
+    // Two policies with the same fingerprint make the same passwords.
+    pub fn fingerprint(&self) -> String {
+
+        // Writes a list of characters with its length in front.
+        fn write_chars(hasher: &mut Sha512, tag: &[u8], chars: &[char]) {
+            hasher.update(tag);
+            hasher.update((chars.len() as u64).to_be_bytes());
+            for c in chars {
+                hasher.update(u32::from(*c).to_be_bytes());
+            }
+        }
+
+        let mut hasher = Sha512::new();
+
+        for class in &self.allowed {
+            write_chars(&mut hasher, b"a", &class.chars());
+        }
+        for (class, min) in &self.required {
+            write_chars(&mut hasher, b"r", &class.chars());
+            hasher.update((*min as u64).to_be_bytes());
+        }
+        write_chars(&mut hasher, b"f", &self.forbidden);
+
+        // Writes the numbers, with 0 standing for "no limit".
+        for (tag, value) in [
+            (b"c", self.max_consecutive.map_or(0, |n| n as u64 + 1)),
+            (b"l", self.min_length.map_or(0, |n| n.saturating_add(1))),
+            (b"L", self.max_length.map_or(0, |n| n.saturating_add(1))),
+        ] {
+            hasher.update(tag);
+            hasher.update(value.to_be_bytes());
+        }
+
+        hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    // Checks the length against the policy.
+    pub(crate) fn check_length(&self, length: u64) -> Result<(), PassgenError> {
+        if self.min_length.is_some_and(|min| length < min) {
+            return Err(PassgenError::InvalidPolicy(
+                format!("the length must be at least {}", self.min_length.unwrap_or(0))));
+        }
+        if self.max_length.is_some_and(|max| length > max) {
+            return Err(PassgenError::InvalidPolicy(
+                format!("the length must be at most {}", self.max_length.unwrap_or(0))));
+        }
+
+        Ok(())
+    }
+
+    // The length nearest to `length` that the policy accepts. A minimum
+    // above the maximum wins, and check_length() refuses it.
+    pub fn clamp_length(&self, length: u64) -> u64 {
+        let length = self.max_length.map_or(length, |max| length.min(max));
+        self.min_length.map_or(length, |min| length.max(min))
+    }
+
+    // Removes forbidden and repeated characters.
+    fn filter(&self, chars: Vec<char>) -> Vec<char> {
+        let mut output: Vec<char> = Vec::new();
+
+        for c in chars {
+            if !self.forbidden.contains(&c) && !output.contains(&c) {
+                output.push(c);
+            }
+        }
+
+        output
+    }
+
+    // Maps the key onto a password of `length` characters that follows
+    // every rule. `alphabet` is used when the policy allows no classes itself.
+    pub fn generate(
+        &self,
+        key: &[u8],
+        alphabet: &Alphabet,
+        length: usize) -> Result<String, PassgenError> {
+
+        self.generate_stream(&mut ByteStream::new(key), alphabet, length)
+    }
+
+    // Like generate(), but draws from the next bytes of the stream.
+    pub(crate) fn generate_stream(
+        &self,
+        stream: &mut ByteStream,
+        alphabet: &Alphabet,
+        length: usize) -> Result<String, PassgenError> {
+
+        self.check_length(length as u64)?;
+
+        // Every required class, without forbidden characters.
+        let mut required = Vec::new();
+        for (class, min) in &self.required {
+            let chars = self.filter(class.chars());
+
+            if chars.is_empty() && *min > 0 {
+                return Err(PassgenError::InvalidPolicy(
+                    format!("every character of a required class is forbidden: {:?}", class)));
+            }
+
+            required.push((chars, *min));
+        }
+
+        // Every character the rest of the password can use.
+        let mut allowed = if self.allowed.is_empty() {
+            alphabet.chars().to_vec()
+        } else {
+            self.allowed.iter().flat_map(CharClass::chars).collect()
+        };
+        for (chars, _) in &required {
+            allowed.extend(chars);
+        }
+        let allowed = self.filter(allowed);
+
+        if allowed.is_empty() {
+            return Err(PassgenError::InvalidPolicy(String::from("no characters are allowed")));
+        }
+
+        // Checks that the required characters fit in the password.
+        let required_count: usize = required.iter().map(|(_, min)| min).sum();
+        if required_count > length {
+            return Err(PassgenError::InvalidPolicy(
+                format!("{} characters are required but the length is {}", required_count, length)));
+        }
+
+        // One allowed character can only be repeated so far.
+        if allowed.len() == 1 && self.max_consecutive.is_some_and(|max| length > max) {
+            return Err(PassgenError::InvalidPolicy(
+                String::from("the repeat limit can't be met with one character")));
+        }
+
+        let mut password = Zeroizing::new(Vec::with_capacity(length));
+
+        // Picks the required characters first.
+        for (chars, min) in &required {
+            for _ in 0..*min {
+                password.push(chars[stream.uniform(chars.len())]);
+            }
+        }
+
+        // Kept in case they have to be laid out again.
+        let mut picked = Zeroizing::new(Vec::with_capacity(password.len()));
+        picked.extend_from_slice(&password);
+
+        // Fills the rest from every allowed character.
+        while password.len() < length {
+            password.push(allowed[stream.uniform(allowed.len())]);
+        }
+
+        // Shuffles the password so the required characters can be anywhere.
+        for index in (1..password.len()).rev() {
+            password.swap(index, stream.uniform(index + 1));
+        }
+
+        // A run that is too long lays the required characters out again.
+        if let Some(max) = self.max_consecutive.filter(|max| longest_run(&password) > *max) {
+            password = arrange(stream, &picked, length - picked.len(), &allowed, max)?;
+        }
+
+        // Sized for every character, so the String never leaves a copy behind.
+        let mut output = String::with_capacity(password.iter().map(|c| c.len_utf8()).sum());
+        output.extend(password.iter());
+
+        Ok(output)
+    }
+}
+
+// The longest run of one repeated character.
+fn longest_run(password: &[char]) -> usize {
+    password.chunk_by(|a, b| a == b).map(<[char]>::len).max().unwrap_or(0)
+}
+
+// Lays out `required` and `fills` more allowed characters from left to
+// right, so no character repeats more than `max` times in a row. Every
+// character is drawn from the ones that still leave a way to finish, the
+// required ones weighted by how many of each are left.
+fn arrange(
+    stream: &mut ByteStream,
+    required: &[char],
+    mut fills: usize,
+    allowed: &[char],
+    max: usize) -> Result<Zeroizing<Vec<char>>, PassgenError> {
+
+    // How many of each required character are left, in the order they were drawn.
+    let mut counts: Zeroizing<Vec<(char, usize)>> = Zeroizing::new(Vec::with_capacity(required.len()));
+    for c in required {
+        match counts.iter_mut().find(|(other, _)| other == c) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((*c, 1)),
+        }
+    }
+
+    let length = required.len() + fills;
+    let mut password = Zeroizing::new(Vec::with_capacity(length));
+
+    // The last character and how many times in a row it was used.
+    let mut last = None;
+
+    while password.len() < length {
+
+        // The run a character would make next, if it can go next at all.
+        let run_of = |c: char| match last {
+            Some((other, run)) if other == c => (run < max).then_some(run + 1),
+            _ => Some(1),
+        };
+
+        // Whether the rest can still be laid out after `c`. Every character
+        // left can be cut into runs of `max` with the others between them,
+        // the first one shortened by the run it would join.
+        let fits = |counts: &[(char, usize)], fills: usize, c: char, run: usize| {
+            let left = counts.iter().map(|(_, count)| count).sum::<usize>() + fills;
+            counts.iter().all(|(other, count)| {
+                let first = if *other == c { max - run } else { max };
+                *count <= first + max * (left - count)
+            })
+        };
+
+        // Each required character that can go next, and how many are left.
+        let mut options: Zeroizing<Vec<(Option<char>, usize)>> = Zeroizing::new(Vec::with_capacity(counts.len() + 1));
+        for index in 0..counts.len() {
+            let (c, count) = counts[index];
+            if let Some(run) = run_of(c).filter(|_| count > 0) {
+                counts[index].1 -= 1;
+                if fits(&counts, fills, c, run) {
+                    options.push((Some(c), count));
+                }
+                counts[index].1 += 1;
+            }
+        }
+
+        // The allowed characters that can fill the next place.
+        let fillers: Vec<char> = allowed
+            .iter()
+            .copied()
+            .filter(|c| fills > 0 && run_of(*c).is_some_and(|run| fits(&counts, fills - 1, *c, run)))
+            .collect();
+        if !fillers.is_empty() {
+            options.push((None, fills));
+        }
+
+        let total: usize = options.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return Err(PassgenError::InvalidPolicy(String::from("the repeat limit can't be met")));
+        }
+
+        // Walks the weights to the option that was drawn.
+        let mut pick = stream.uniform(total);
+        let mut choice = None;
+        for (option, weight) in options.iter() {
+            if pick < *weight {
+                choice = *option;
+                break;
+            }
+            pick -= weight;
+        }
+
+        let c = match choice {
+            Some(c) => {
+                if let Some((_, count)) = counts.iter_mut().find(|(other, _)| *other == c) {
+                    *count -= 1;
+                }
+                c
+            }
+            None => {
+                fills -= 1;
+                fillers[stream.uniform(fillers.len())]
+            }
+        };
+
+        last = Some((c, run_of(c).unwrap_or(1)));
+        password.push(c);
+    }
+
+    Ok(password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A policy like "one digit, one uppercase, one of !@#$, max 20 chars".
+    fn site_policy() -> Policy {
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Lower);
+        policy.require(CharClass::Digit, 1);
+        policy.require(CharClass::Upper, 1);
+        policy.require(CharClass::Custom("!@#$".chars().collect()), 1);
+        policy.set_max_consecutive(2).unwrap();
+        policy.set_max_length(20);
+        policy
+    }
+
+    // Tests that a length is moved into the policy's range.
+    #[test]
+    fn clamp_length_stays_in_range() {
+
+        let mut policy = site_policy();
+        assert_eq!(policy.clamp_length(32), 20);
+        assert_eq!(policy.clamp_length(4), 4);
+
+        policy.set_min_length(8);
+        assert_eq!(policy.clamp_length(4), 8);
+        assert_eq!(policy.clamp_length(12), 12);
+        assert_eq!(Policy::new().clamp_length(32), 32);
+    }
+
+    // Tests that every generated password follows the policy.
+    #[test]
+    fn generate_follows_policy() {
+
+        let policy = site_policy();
+
+        for seed in 0..200u32 {
+            let password = policy.generate(&seed.to_be_bytes(), &Alphabet::default(), 12).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+
+            assert_eq!(chars.len(), 12);
+            assert!(chars.iter().any(char::is_ascii_digit), "{}", password);
+            assert!(chars.iter().any(char::is_ascii_uppercase), "{}", password);
+            assert!(chars.iter().any(|c| "!@#$".contains(*c)), "{}", password);
+            assert!(chars.iter().all(|c| c.is_ascii_alphanumeric() || "!@#$".contains(*c)), "{}", password);
+            assert!(longest_run(&chars) <= 2, "{}", password);
+        }
+    }
+
+    // Tests that the same key always gives the same password.
+    #[test]
+    fn generate_is_deterministic() {
+
+        let policy = site_policy();
+
+        let first = policy.generate(b"apple", &Alphabet::default(), 16).unwrap();
+        let second = policy.generate(b"apple", &Alphabet::default(), 16).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    // Tests that forbidden characters never show up.
+    #[test]
+    fn generate_skips_forbidden() {
+
+        let mut policy = Policy::new();
+        policy.forbid("aeiou0O1lI");
+
+        let password = policy.generate(b"apple", &Alphabet::default(), 500).unwrap();
+
+        assert!(!password.contains(|c| "aeiou0O1lI".contains(c)));
+    }
+
+    // Tests that a tight repeat limit still works on a small alphabet.
+    #[test]
+    fn generate_max_consecutive_one() {
+
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Custom(vec!['a', 'b']));
+        policy.set_max_consecutive(1).unwrap();
+
+        let password = policy.generate(b"apple", &Alphabet::default(), 10).unwrap();
+
+        assert!(password == "ababababab" || password == "bababababa", "{}", password);
+    }
+
+    // Tests that a repeat limit is met at any length without drawing the
+    // whole password again.
+    #[test]
+    fn generate_breaks_long_runs() {
+
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Custom(vec!['a', 'b']));
+        policy.set_max_consecutive(1).unwrap();
+        let password: Vec<char> = policy.generate(b"apple", &Alphabet::default(), 40).unwrap().chars().collect();
+        assert_eq!(longest_run(&password), 1);
+
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Digit);
+        policy.set_max_consecutive(1).unwrap();
+        for seed in 0..20u32 {
+            let password: Vec<char> = policy.generate(&seed.to_be_bytes(), &Alphabet::default(), 200).unwrap().chars().collect();
+            assert_eq!(longest_run(&password), 1);
+            assert!(password.iter().all(char::is_ascii_digit));
+        }
+
+        // A class of one character is spread out between the others.
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Custom(vec!['a', 'b']));
+        policy.require(CharClass::Custom(vec!['a']), 10);
+        policy.set_max_consecutive(1).unwrap();
+        for seed in 0..20u32 {
+            let password = policy.generate(&seed.to_be_bytes(), &Alphabet::default(), 30).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(longest_run(&chars), 1, "{}", password);
+            assert!(password.matches('a').count() >= 10, "{}", password);
+        }
+
+        // Passwords that met the limit on the first draw are the ones they always were.
+        let policy = site_policy();
+        assert_eq!(policy.generate(b"apple", &Alphabet::default(), 16).unwrap(), "UCcGo9QcU!IoqS27");
+        assert_eq!(policy.generate(b"hello", &Alphabet::default(), 16).unwrap(), "B!4ML@j3GDXOJ!G7");
+
+        let mut policy = Policy::new();
+        policy.set_max_consecutive(1).unwrap();
+        assert_eq!(policy.generate(b"apple", &Alphabet::default(), 32).unwrap(), "ck8(/sqG{a=31=#!eIOI/l-3Y$UM?0_}");
+
+        assert!(policy.set_max_consecutive(0).is_err());
+    }
+
+    // Tests that any change to the rules changes the fingerprint.
+    #[test]
+    fn fingerprint_tracks_rules() {
+
+        let comparitor = site_policy().fingerprint();
+        assert_eq!(site_policy().fingerprint(), comparitor);
+        assert_eq!(comparitor.len(), 16);
+
+        let mut policy = site_policy();
+        policy.set_max_consecutive(3).unwrap();
+        assert_ne!(policy.fingerprint(), comparitor);
+
+        let mut policy = site_policy();
+        policy.forbid("x");
+        assert_ne!(policy.fingerprint(), comparitor);
+
+        assert_ne!(Policy::new().fingerprint(), comparitor);
+    }
+
+    // Tests that impossible policies are errors.
+    #[test]
+    fn generate_rejects_impossible() {
+
+        let policy = site_policy();
+        assert!(policy.generate(b"apple", &Alphabet::default(), 21).is_err());
+        assert!(policy.generate(b"apple", &Alphabet::default(), 2).is_err());
+
+        let mut policy = Policy::new();
+        policy.require(CharClass::Digit, 1);
+        policy.forbid("0123456789");
+        assert!(policy.generate(b"apple", &Alphabet::default(), 8).is_err());
+
+        let mut policy = Policy::new();
+        policy.allow(CharClass::Custom(vec!['a']));
+        policy.set_max_consecutive(2).unwrap();
+        assert!(policy.generate(b"apple", &Alphabet::default(), 3).is_err());
+    }
+}
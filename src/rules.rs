@@ -0,0 +1,284 @@
+// Parses the passwordrules syntax that sites publish, for example
+// "minlength: 8; required: lower; required: upper; allowed: [-().&@?'#,/"+]; max-consecutive: 2"
+//
+// Spaces are never generated, so "special" and "ascii-printable" leave out
+// the space character, and "unicode" is treated as "ascii-printable".
+
+use crate::error::PassgenError;
+
+use crate::policy::{CharClass, Policy};
+
+// Turns a passwordrules String into a Policy. Unknown properties are
+// ignored, as the syntax asks, so newer rules don't break older parsers.
+// The allowed characters are the required and allowed classes together,
+// so rules with only required classes allow nothing else.
+pub fn parse(rules: &str) -> Result<Policy, PassgenError> {
+
+    let mut policy = Policy::new();
+
+    // The tightest of each number seen so far.
+    let mut min_length: Option<u64> = None;
+    let mut max_length: Option<u64> = None;
+    let mut max_consecutive: Option<usize> = None;
+
+    // Without any allowed rule only the required classes may be used.
+    let mut required = Vec::new();
+    let mut any_allowed = false;
+
+    for rule in split_rules(rules)? {
+
+        // Skips empty rules, like the one after a trailing ';'.
+        if rule.trim().is_empty() {
+            continue;
+        }
+
+        let (name, value) = rule.split_once(':')
+            .ok_or_else(|| PassgenError::InvalidPolicy(format!("'{}' has no ':'", rule.trim())))?;
+
+        let value = value.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "required" => {
+
+                // One character from any of the listed classes is required.
+                let chars: Vec<char> = parse_classes(value)?
+                    .iter()
+                    .flat_map(CharClass::chars)
+                    .collect();
+                policy.require(CharClass::Custom(chars.clone()), 1);
+                required.push(CharClass::Custom(chars));
+            }
+            "allowed" => {
+                any_allowed = true;
+                for class in parse_classes(value)? {
+                    policy.allow(class);
+                }
+            }
+            "minlength" => {
+                let n = parse_number(value)?;
+                min_length = Some(min_length.map_or(n, |old| old.max(n)));
+            }
+            "maxlength" => {
+                let n = parse_number(value)?;
+                max_length = Some(max_length.map_or(n, |old| old.min(n)));
+            }
+            "max-consecutive" => {
+                let n = parse_number(value)?;
+                let n = usize::try_from(n)
+                    .map_err(|_| PassgenError::Overflow(format!("a max-consecutive of {}", n)))?;
+                max_consecutive = Some(max_consecutive.map_or(n, |old| old.min(n)));
+            }
+            _ => {}
+        }
+    }
+
+    if !any_allowed {
+        for class in required {
+            policy.allow(class);
+        }
+    }
+
+    if let Some(min) = min_length {
+        policy.set_min_length(min);
+    }
+    if let Some(max) = max_length {
+        policy.set_max_length(max);
+    }
+    if let Some(max) = max_consecutive {
+        policy.set_max_consecutive(max)?;
+    }
+
+    Ok(policy)
+}
+
+// Splits the rules on ';', but not inside a custom class like [;,].
+fn split_rules(rules: &str) -> Result<Vec<String>, PassgenError> {
+
+    let mut output = Vec::new();
+    let mut current = String::new();
+    let mut chars = rules.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => output.push(std::mem::take(&mut current)),
+            '[' => {
+                current.push(c);
+                current.push_str(&read_custom(&mut chars)?);
+                current.push(']');
+            }
+            _ => current.push(c),
+        }
+    }
+
+    output.push(current);
+
+    Ok(output)
+}
+
+// Reads the inside of a custom class after its '['. A ']' ends the class
+// unless another ']' comes right after it, which lets ']' be listed last.
+fn read_custom(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<String, PassgenError> {
+
+    let mut inside = String::new();
+
+    while let Some(c) = chars.next() {
+        if c == ']' && chars.peek() != Some(&']') {
+            return Ok(inside);
+        }
+        inside.push(c);
+    }
+
+    Err(PassgenError::InvalidPolicy(String::from("a '[' is never closed")))
+}
+
+// Parses a comma separated list of classes, like "upper, digit, [-_]".
+fn parse_classes(value: &str) -> Result<Vec<CharClass>, PassgenError> {
+
+    let mut classes = Vec::new();
+    let mut chars = value.chars().peekable();
+    let mut name = String::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ',' => finish_name(&mut name, &mut classes)?,
+            '[' => {
+                finish_name(&mut name, &mut classes)?;
+                classes.push(CharClass::Custom(read_custom(&mut chars)?.chars().collect()));
+            }
+            _ => name.push(c),
+        }
+    }
+
+    finish_name(&mut name, &mut classes)?;
+
+    if classes.is_empty() {
+        return Err(PassgenError::InvalidPolicy(String::from("a rule lists no characters")));
+    }
+
+    Ok(classes)
+}
+
+// Turns a class name into classes, and clears it for the next one.
+fn finish_name(name: &mut String, classes: &mut Vec<CharClass>) -> Result<(), PassgenError> {
+
+    let trimmed = name.trim().to_ascii_lowercase();
+    name.clear();
+
+    match trimmed.as_str() {
+        "" => {}
+        "upper" => classes.push(CharClass::Upper),
+        "lower" => classes.push(CharClass::Lower),
+        "digit" => classes.push(CharClass::Digit),
+        "special" => classes.push(CharClass::Symbol),
+        "ascii-printable" | "unicode" => {
+            classes.extend([CharClass::Lower, CharClass::Upper, CharClass::Digit, CharClass::Symbol]);
+        }
+        _ => return Err(PassgenError::InvalidPolicy(format!("'{}' is not a character class", trimmed))),
+    }
+
+    Ok(())
+}
+
+// Parses a whole number value.
+fn parse_number(value: &str) -> Result<u64, PassgenError> {
+    value.parse::<u64>()
+        .map_err(|_| PassgenError::InvalidPolicy(format!("'{}' is not a valid integer", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::alphabet::Alphabet;
+
+    // Tests the example rules from a real site.
+    #[test]
+    fn parse_example() {
+
+        let policy = parse("minlength: 8; required: lower; required: upper; \
+            allowed: [-().&@?'#,/\"+]; max-consecutive: 2").unwrap();
+
+        let mut comparitor = Policy::new();
+        comparitor.require(CharClass::Custom(CharClass::Lower.chars()), 1);
+        comparitor.require(CharClass::Custom(CharClass::Upper.chars()), 1);
+        comparitor.allow(CharClass::Custom("-().&@?'#,/\"+".chars().collect()));
+        comparitor.set_min_length(8);
+        comparitor.set_max_consecutive(2).unwrap();
+
+        assert_eq!(policy, comparitor);
+    }
+
+    // Tests that a generated password follows the parsed rules.
+    #[test]
+    fn parse_and_generate() {
+
+        let policy = parse("required: digit; required: [!@#$]; allowed: lower; maxlength: 20").unwrap();
+        let password = policy.generate(b"apple", &Alphabet::default(), 20).unwrap();
+
+        assert!(password.contains(|c: char| c.is_ascii_digit()), "{}", password);
+        assert!(password.contains(|c| "!@#$".contains(c)), "{}", password);
+        assert!(password.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()
+            || "!@#$".contains(c)), "{}", password);
+        assert!(policy.generate(b"apple", &Alphabet::default(), 21).is_err());
+    }
+
+    // Tests that without an allowed rule only the required classes are used.
+    #[test]
+    fn parse_allows_required() {
+
+        let policy = parse("minlength: 12; maxlength: 12; required: lower; required: upper").unwrap();
+
+        let mut comparitor = Policy::new();
+        comparitor.require(CharClass::Custom(CharClass::Lower.chars()), 1);
+        comparitor.require(CharClass::Custom(CharClass::Upper.chars()), 1);
+        comparitor.allow(CharClass::Custom(CharClass::Lower.chars()));
+        comparitor.allow(CharClass::Custom(CharClass::Upper.chars()));
+        comparitor.set_min_length(12);
+        comparitor.set_max_length(12);
+        assert_eq!(policy, comparitor);
+
+        for seed in 0..50u32 {
+            let password = policy.generate(&seed.to_be_bytes(), &Alphabet::default(), 12).unwrap();
+            assert!(password.chars().all(|c| c.is_ascii_alphabetic()), "{}", password);
+        }
+    }
+
+    // Tests custom classes holding ';', ',' and a trailing ']'.
+    #[test]
+    fn parse_custom_edge_cases() {
+
+        let policy = parse("allowed: [;,-]]; required: upper, [_]").unwrap();
+
+        let mut comparitor = Policy::new();
+        comparitor.allow(CharClass::Custom(vec![';', ',', '-', ']']));
+        let mut upper = CharClass::Upper.chars();
+        upper.push('_');
+        comparitor.require(CharClass::Custom(upper), 1);
+
+        assert_eq!(policy, comparitor);
+    }
+
+    // Tests that repeated numbers keep the tightest value and unknown rules are ignored.
+    #[test]
+    fn parse_tightest_numbers() {
+
+        let policy = parse("maxlength: 30; maxlength: 20; minlength: 4; minlength: 8; \
+            max-consecutive: 3; max-consecutive: 2; passwordsafe: yes;").unwrap();
+
+        let mut comparitor = Policy::new();
+        comparitor.set_min_length(8);
+        comparitor.set_max_length(20);
+        comparitor.set_max_consecutive(2).unwrap();
+
+        assert_eq!(policy, comparitor);
+    }
+
+    // Tests that malformed rules are errors.
+    #[test]
+    fn parse_rejects_bad_rules() {
+        for rules in ["required lower", "required: vowels", "allowed: [abc", "minlength: eight", "required:",
+            "max-consecutive: 0"] {
+            assert!(matches!(parse(rules), Err(PassgenError::InvalidPolicy(_))), "{}", rules);
+        }
+    }
+}
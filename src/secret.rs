@@ -0,0 +1,119 @@
+// Buffers for the stretched key and the bytes read from it. They are wiped
+// when they are dropped. The library has no unsafe code, so it can't pin
+// them in RAM itself, but a program can with set_lock_hook().
+
+use std::any::Any;
+use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
+
+use zeroize::Zeroize;
+
+// Called with the address and size of a key buffer, before anything is
+// written to it. What it returns is kept until the buffer has been wiped,
+// then dropped before the buffer is freed.
+pub type LockHook = fn(usize, usize) -> Box<dyn Any + Send + Sync>;
+
+static LOCK_HOOK: OnceLock<LockHook> = OnceLock::new();
+
+// Sets the hook every key buffer is locked with. Only the first hook is
+// kept, so call it before anything is hashed.
+pub fn set_lock_hook(hook: LockHook) {
+    let _ = LOCK_HOOK.set(hook);
+}
+
+// A buffer that never grows past the capacity it was made with, so the
+// bytes never move out from under the lock or leave a copy behind.
+pub(crate) struct KeyBuffer {
+    bytes: Vec<u8>,
+    lock: Option<Box<dyn Any + Send + Sync>>
+}
+
+impl KeyBuffer {
+
+    // An empty buffer with room for `capacity` bytes, locked by the hook
+    // if one is set.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+
+        let bytes = Vec::with_capacity(capacity);
+        let lock = LOCK_HOOK.get()
+            .filter(|_| bytes.capacity() > 0)
+            .map(|hook| hook(bytes.as_ptr() as usize, bytes.capacity()));
+
+        Self {
+            bytes,
+            lock
+        }
+    }
+}
+
+impl Default for KeyBuffer {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+// Copies into a buffer of its own, with a lock of its own.
+impl Clone for KeyBuffer {
+    fn clone(&self) -> Self {
+        let mut buffer = Self::with_capacity(self.bytes.capacity());
+        buffer.bytes.extend_from_slice(&self.bytes);
+        buffer
+    }
+}
+
+impl Deref for KeyBuffer {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Vec<u8> {
+        &self.bytes
+    }
+}
+
+impl DerefMut for KeyBuffer {
+    fn deref_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.bytes
+    }
+}
+
+// Wipes the whole capacity, then unlocks it. The Vec frees it afterwards.
+impl Drop for KeyBuffer {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+        self.lock = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    // The buffers the test hook was called with.
+    static LOCKED: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+    fn record(start: usize, length: usize) -> Box<dyn Any + Send + Sync> {
+        LOCKED.lock().unwrap().push((start, length));
+        Box::new(())
+    }
+
+    // Tests that the hook sees the whole buffer before it is written to.
+    #[test]
+    fn hook_sees_every_buffer() {
+
+        set_lock_hook(record);
+
+        let mut buffer = KeyBuffer::with_capacity(128);
+        let comparitor = (buffer.as_ptr() as usize, buffer.capacity());
+        assert!(LOCKED.lock().unwrap().contains(&comparitor));
+
+        // The copy is locked where it lives, not where the original does.
+        buffer.extend_from_slice(b"apple");
+        let copy = buffer.clone();
+        assert_eq!(&copy[..], b"apple");
+        assert!(LOCKED.lock().unwrap().contains(&(copy.as_ptr() as usize, copy.capacity())));
+
+        // Empty buffers have nothing to lock.
+        assert!(KeyBuffer::default().lock.is_none());
+    }
+}
@@ -0,0 +1,201 @@
+// Derives a separate password for every site from one master secret.
+
+use crate::error::PassgenError;
+
+use crate::hashing_arguments::HashArguments;
+
+// Every site password is made with the version it was first made with.
+use crate::algorithm::Algorithm;
+
+// Wipes the master secret and everything made from it once they are dropped.
+use zeroize::{Zeroize, Zeroizing};
+
+use std::fmt::{self, Write};
+
+// Tags the message so site seeds can never equal a plain seed's message.
+const DOMAIN_TAG: &[u8] = b"neo_passgen/site/v1";
+
+// The inputs for one site's password. The master secret is wiped from
+// memory when the request is dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SiteRequest {
+    pub master: String,
+    pub site: String,
+    pub login: String,
+    pub counter: u64
+}
+
+impl SiteRequest {
+
+    // A simple constructor function for the type. Domain names are not case
+    // sensitive, so the site is lowercased.
+    pub fn new(master: String, site: &str, login: String, counter: u64) -> Self {
+        Self {
+            master,
+            site: site.to_ascii_lowercase(),
+            login,
+            counter
+        }
+    }
+
+    // Encodes every field with its length in front, so no two requests can
+    // produce the same message ("ab" + "c" is not "a" + "bc").
+    fn message(&self, length: u64) -> Zeroizing<Vec<u8>> {
+
+        // Sized up front so the message never moves and leaves a copy behind.
+        let fields = [DOMAIN_TAG, self.master.as_bytes(), self.site.as_bytes(), self.login.as_bytes()];
+        let capacity = fields.iter().map(|field| field.len() + 8).sum::<usize>() + 16;
+        let mut message = Zeroizing::new(Vec::with_capacity(capacity));
+
+        for field in fields {
+            message.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            message.extend_from_slice(field);
+        }
+
+        message.extend_from_slice(&self.counter.to_be_bytes());
+        message.extend_from_slice(&length.to_be_bytes());
+
+        message
+    }
+
+    // The seed HashArguments hashes for this site: the hex encoded message.
+    // Prefix mode passes a length of 0, so every length gets the same seed.
+    pub fn seed(&self, length: u64) -> Zeroizing<String> {
+
+        let message = self.message(length);
+        let mut seed = Zeroizing::new(String::with_capacity(message.len() * 2));
+
+        for byte in message.iter() {
+            // Writing to a String never fails.
+            let _ = write!(seed, "{:02X}", byte);
+        }
+
+        seed
+    }
+
+    // Builds the HashArguments for this site, so the kdf can still be
+    // changed before hashing. The algorithm is never taken from the
+    // default, so a new default can't change a saved site's password.
+    // With `prefix`, every shorter password is the start of a longer one;
+    // it can't be turned on afterwards, since the seed depends on it.
+    pub fn hash_arguments(
+        &self,
+        algorithm: Algorithm,
+        hashcount: u64,
+        length: u64,
+        threads: u64,
+        prefix: bool) -> Result<HashArguments, PassgenError> {
+
+        let mut seed = self.seed(if prefix { 0 } else { length });
+        let mut args = HashArguments::new(std::mem::take(&mut *seed), hashcount, length, threads)?;
+        args.set_algorithm(algorithm);
+        args.set_prefix(prefix);
+
+        Ok(args)
+    }
+
+    // Runs the whole backend and returns the site's password.
+    pub fn derive(
+        &self,
+        algorithm: Algorithm,
+        hashcount: u64,
+        length: u64,
+        threads: u64,
+        prefix: bool) -> Result<String, PassgenError> {
+
+        self.hash_arguments(algorithm, hashcount, length, threads, prefix)?.run()
+    }
+}
+
+impl Drop for SiteRequest {
+    fn drop(&mut self) {
+        self.master.zeroize();
+    }
+}
+
+// Leaves the master secret out, so it never ends up in logs.
+impl fmt::Debug for SiteRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SiteRequest")
+            .field("master", &"[redacted]")
+            .field("site", &self.site)
+            .field("login", &self.login)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(master: &str, site: &str, login: &str, counter: u64) -> SiteRequest {
+        SiteRequest::new(String::from(master), site, String::from(login), counter)
+    }
+
+    // Tests that the output is correct for a given input.
+    #[test]
+    fn site_output() {
+
+        let output = request("hunter2", "example.com", "alice", 1).derive(Algorithm::V2, 100, 24, 1, false).unwrap();
+
+        // Correct output for the site, as first made with npg2.
+        let comparitor = String::from(r#"TgzZZ41tEtraji"AL9YPk*<X"#);
+
+        assert_eq!(output, comparitor);
+
+        // Every newer version keeps its own password for the same site.
+        for (algorithm, comparitor) in [(Algorithm::V3, r#"U1_q8,x:R:"xae(c"0"5hR7s"#),
+            (Algorithm::V4, r#"qj`@9L:(tD,Nxha@X7#K4xQb"#), (Algorithm::V5, r#"n|bR(^}{Mb]S*Kt$2)9pL0=#"#)] {
+            let output = request("hunter2", "example.com", "alice", 1).derive(algorithm, 100, 24, 1, false).unwrap();
+            assert_eq!(output, comparitor, "{}", algorithm);
+        }
+    }
+
+    // Tests that moving bytes between fields changes the password.
+    #[test]
+    fn fields_are_separated() {
+
+        let comparitor = request("ab", "c.com", "d", 1).derive(Algorithm::V3, 1, 24, 1, false).unwrap();
+
+        assert_ne!(request("a", "bc.com", "d", 1).derive(Algorithm::V3, 1, 24, 1, false).unwrap(), comparitor);
+        assert_ne!(request("ab", "c.co", "md", 1).derive(Algorithm::V3, 1, 24, 1, false).unwrap(), comparitor);
+        assert_ne!(request("ab", "c.com", "d", 2).derive(Algorithm::V3, 1, 24, 1, false).unwrap(), comparitor);
+    }
+
+    // Tests that prefix mode makes shorter passwords the start of longer ones.
+    #[test]
+    fn prefix_passwords() {
+
+        let comparitor = request("hunter2", "example.com", "alice", 1).derive(Algorithm::V5, 1, 40, 1, true).unwrap();
+
+        for length in [1, 12, 24] {
+            let output = request("hunter2", "example.com", "alice", 1).derive(Algorithm::V5, 1, length, 1, true).unwrap();
+            assert!(comparitor.starts_with(&output), "{}", length);
+        }
+
+        // Without it, the length changes the whole password.
+        let output = request("hunter2", "example.com", "alice", 1).derive(Algorithm::V5, 1, 24, 1, false).unwrap();
+        assert!(!comparitor.starts_with(&output));
+    }
+
+    // Tests that Debug never shows the master secret.
+    #[test]
+    fn debug_redacts_master() {
+
+        let output = format!("{:?}", request("hunter2", "example.com", "alice", 1));
+
+        assert!(!output.contains("hunter2"));
+        assert!(output.contains("example.com"));
+    }
+
+    // Tests that the site name is not case sensitive.
+    #[test]
+    fn site_is_lowercased() {
+
+        let lower = request("hunter2", "example.com", "alice", 1).derive(Algorithm::V3, 1, 24, 1, false).unwrap();
+        let upper = request("hunter2", "Example.COM", "alice", 1).derive(Algorithm::V3, 1, 24, 1, false).unwrap();
+
+        assert_eq!(lower, upper);
+    }
+}
@@ -0,0 +1,198 @@
+// Password shapes like "Cvcvnocvcv", for passwords that are easier to read
+// out or type on a phone.
+//
+// Each letter of a template stands for a set of characters, the same ones
+// Master Password uses:
+//
+//   V  an uppercase vowel          v  a lowercase vowel
+//   C  an uppercase consonant      c  a lowercase consonant
+//   A  an uppercase letter         a  any letter
+//   n  a digit                     o  a symbol
+//   x  a letter, digit or symbol
+//
+// A preset holds several templates of the same length. Which one is used,
+// and every character in it, is drawn from the key without bias.
+
+use crate::error::PassgenError;
+
+use crate::alphabet::ByteStream;
+
+// The names of the built in templates, strongest first.
+pub const PRESETS: [&str; 5] = ["maximum", "long", "medium", "basic", "pin"];
+
+// The templates behind each preset.
+const MAXIMUM: &[&str] = &["anoxxxxxxxxxxxxxxxxx", "axxxxxxxxxxxxxxxxxno"];
+const LONG: &[&str] = &[
+    "CvcvnoCvcvCvcv", "CvcvCvcvnoCvcv", "CvcvCvcvCvcvno", "CvccnoCvcvCvcv", "CvccCvcvnoCvcv",
+    "CvccCvcvCvcvno", "CvcvnoCvccCvcv", "CvcvCvccnoCvcv", "CvcvCvccCvcvno", "CvcvnoCvcvCvcc",
+    "CvcvCvcvnoCvcc", "CvcvCvcvCvccno", "CvccnoCvccCvcv", "CvccCvccnoCvcv", "CvccCvccCvcvno",
+    "CvcvnoCvccCvcc", "CvcvCvccnoCvcc", "CvcvCvccCvccno", "CvccnoCvcvCvcc", "CvccCvcvnoCvcc",
+    "CvccCvcvCvccno",
+];
+const MEDIUM: &[&str] = &["CvcnoCvc", "CvcCvcno"];
+const BASIC: &[&str] = &["aaanaaan", "aannaaan", "aaannaaa"];
+const PIN: &[&str] = &["nnnn"];
+
+// A set of templates that all make passwords of the same length.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    id: String,
+    patterns: Vec<String>
+}
+
+impl Template {
+
+    // One custom template, like "Cvcvnocvcv".
+    pub fn new(pattern: &str) -> Result<Self, PassgenError> {
+
+        if pattern.is_empty() {
+            return Err(PassgenError::InvalidTemplate(String::from("the template is empty")));
+        }
+
+        if let Some(letter) = pattern.chars().find(|&letter| class(letter).is_none()) {
+            return Err(PassgenError::InvalidTemplate(format!("'{}' is not a template letter", letter)));
+        }
+
+        Ok(Self {
+            id: pattern.to_owned(),
+            patterns: vec![pattern.to_owned()]
+        })
+    }
+
+    // One of the PRESETS.
+    pub fn preset(name: &str) -> Result<Self, PassgenError> {
+
+        let patterns = match name {
+            "maximum" => MAXIMUM,
+            "long" => LONG,
+            "medium" => MEDIUM,
+            "basic" => BASIC,
+            "pin" => PIN,
+            _ => return Err(PassgenError::InvalidTemplate(format!("'{}' is not a preset", name))),
+        };
+
+        Ok(Self {
+            id: name.to_owned(),
+            patterns: patterns.iter().map(|&pattern| pattern.to_owned()).collect()
+        })
+    }
+
+    // A preset by name, or else a custom template. No preset name is made
+    // of template letters, so the two can't be confused.
+    pub fn from_id(id: &str) -> Result<Self, PassgenError> {
+        if PRESETS.contains(&id) {
+            Self::preset(id)
+        } else {
+            Self::new(id)
+        }
+    }
+
+    // The preset name or the custom template, for parameter strings.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    // The templates one is picked from.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    // The length of every password the template makes.
+    pub fn len(&self) -> usize {
+        self.patterns[0].len()
+    }
+
+    // Whether the template has no positions.
+    pub fn is_empty(&self) -> bool {
+        self.patterns[0].is_empty()
+    }
+
+    // Picks a template from the stream, then fills in every letter.
+    pub(crate) fn generate(&self, stream: &mut ByteStream) -> String {
+
+        let pattern = &self.patterns[stream.uniform(self.patterns.len())];
+
+        pattern
+            .chars()
+            .filter_map(class)
+            .map(|chars| char::from(chars[stream.uniform(chars.len())]))
+            .collect()
+    }
+}
+
+// The characters a template letter stands for.
+fn class(letter: char) -> Option<&'static [u8]> {
+    Some(match letter {
+        'V' => b"AEIOU",
+        'C' => b"BCDFGHJKLMNPQRSTVWXYZ",
+        'v' => b"aeiou",
+        'c' => b"bcdfghjklmnpqrstvwxyz",
+        'A' => b"AEIOUBCDFGHJKLMNPQRSTVWXYZ",
+        'a' => b"AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz",
+        'n' => b"0123456789",
+        'o' => b"@&%?,=[]_:-+*$#!'^~;()/.",
+        'x' => b"AEIOUaeiouBCDFGHJKLMNPQRSTVWXYZbcdfghjklmnpqrstvwxyz0123456789!@#$%^&*()",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that every letter is filled from its own class.
+    #[test]
+    fn generate_follows_the_template() {
+
+        let template = Template::new("Cvcvnocvcv").unwrap();
+        let mut stream = ByteStream::new(b"apple");
+
+        for _ in 0..100 {
+            let password = template.generate(&mut stream);
+
+            assert_eq!(password.len(), 10);
+            for (letter, c) in "Cvcvnocvcv".chars().zip(password.bytes()) {
+                assert!(class(letter).unwrap().contains(&c), "{}", password);
+            }
+        }
+
+        // One byte picks the template, then one byte for each letter.
+        assert_eq!(template.generate(&mut ByteStream::new(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10])), "Cifu5[koma");
+    }
+
+    // Tests that every template of a preset gets picked.
+    #[test]
+    fn generate_picks_every_template() {
+
+        let template = Template::preset("basic").unwrap();
+        let mut stream = ByteStream::new(b"apple");
+
+        let mut shapes = std::collections::HashSet::new();
+        for _ in 0..100 {
+            let password = template.generate(&mut stream);
+            let shape: String = password.chars().map(|c| if c.is_ascii_digit() { 'n' } else { 'a' }).collect();
+            shapes.insert(shape);
+        }
+
+        let comparitor: std::collections::HashSet<String> = BASIC.iter().map(|&pattern| pattern.to_owned()).collect();
+        assert_eq!(shapes, comparitor);
+    }
+
+    // Tests that presets and custom templates are found by id.
+    #[test]
+    fn from_id_finds_templates() {
+
+        for name in PRESETS {
+            let template = Template::from_id(name).unwrap();
+            assert_eq!(template.id(), name);
+            assert!(template.patterns().iter().all(|pattern| pattern.len() == template.len()));
+        }
+
+        assert_eq!(Template::from_id("long").unwrap().len(), 14);
+        assert_eq!(Template::from_id("nnnno").unwrap().patterns(), ["nnnno"]);
+
+        for id in ["", "Cvq", "short"] {
+            assert!(matches!(Template::from_id(id), Err(PassgenError::InvalidTemplate(_))), "{}", id);
+        }
+    }
+}
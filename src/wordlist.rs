@@ -0,0 +1,224 @@
+// Lists of words that passphrases are drawn from.
+//
+// The EFF large wordlist is bundled as it was published, with one
+// "11111<tab>word" line for each roll of five dice, and is used unless
+// another list is loaded. Other lists are files of the same format, or
+// with one word on each line. The words are lowercased, sorted and
+// deduplicated, so only the set of words matters, and a fingerprint of
+// that set is recorded in parameter strings.
+
+use crate::error::PassgenError;
+
+// Fingerprints loaded lists.
+use sha2::{Digest, Sha512};
+
+// Progress messages.
+use log::debug;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Parses the bundled list once.
+use std::sync::OnceLock;
+
+// The EFF large wordlist, from
+// https://www.eff.org/files/2016/07/18/eff_large_wordlist.txt
+const EFF_LARGE: &str = include_str!("wordlists/eff_large_wordlist.txt");
+
+static EFF_LARGE_LIST: OnceLock<Wordlist> = OnceLock::new();
+
+// The words a passphrase is made of, in order.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Wordlist {
+    fingerprint: String,
+    words: Vec<String>
+}
+
+impl Wordlist {
+
+    // The bundled EFF large wordlist: 7776 words, 12.9 bits each.
+    pub fn eff_large() -> Self {
+        EFF_LARGE_LIST
+            .get_or_init(|| Self::parse(EFF_LARGE).expect("the bundled wordlist parses"))
+            .clone()
+    }
+
+    // Reads a wordlist file. See parse() for the format.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PassgenError> {
+
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|e| PassgenError::InvalidPassphrase(
+            format!("can't read the wordlist {}: {}", path.display(), e)))?;
+
+        Self::parse(&text)
+    }
+
+    // Reads a wordlist with one word on each line, optionally after its
+    // dice roll. Blank lines are skipped and words are lowercased, so
+    // "Apple" and "apple" count once, as they would with capitals on.
+    pub fn parse(text: &str) -> Result<Self, PassgenError> {
+
+        let bad = |reason: String| PassgenError::InvalidPassphrase(reason);
+
+        let mut words = Vec::new();
+        for (number, line) in text.trim_start_matches('\u{feff}').lines().enumerate() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields[..] {
+                [] => {}
+                [word] => words.push(word.to_lowercase()),
+                [roll, word] if roll.bytes().all(|byte| byte.is_ascii_digit()) => words.push(word.to_lowercase()),
+                _ => return Err(bad(format!("line {} of the wordlist has more than one word", number + 1))),
+            }
+        }
+
+        let lines = words.len();
+        words.sort_unstable();
+        words.dedup();
+
+        if lines > words.len() {
+            debug!("Dropped {} repeated words from the wordlist", lines - words.len());
+        }
+
+        if words.len() < 2 {
+            return Err(bad(String::from("a wordlist needs at least 2 different words")));
+        }
+
+        Ok(Self::new(words))
+    }
+
+    // A list of words that are already sorted and checked.
+    pub(crate) fn new(words: Vec<String>) -> Self {
+
+        let mut hasher = Sha512::new();
+
+        // Writes each word with its length in front, so no two lists collide.
+        for word in &words {
+            hasher.update((word.len() as u64).to_be_bytes());
+            hasher.update(word.as_bytes());
+        }
+
+        Self {
+            fingerprint: hasher.finalize()[..8].iter().map(|byte| format!("{:02x}", byte)).collect(),
+            words
+        }
+    }
+
+    // A short hash of every word, in order, for parameter strings.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    // The number of words in the list.
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    // Whether the list has no words.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    // The words, in order.
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    // How much each word adds to the strength of a passphrase.
+    pub fn bits_per_word(&self) -> f64 {
+        (self.words.len() as f64).log2()
+    }
+
+    // A word that, followed by the separator, is the start of another word
+    // followed by the separator, and that word. The two could join with other
+    // picks into the same passphrase, like "yo" twice and "yo-yo" with "-".
+    // Without such a pair every passphrase splits back into its words one way.
+    pub fn prefix_pair(&self, separator: &str) -> Option<(&str, &str)> {
+
+        // Whether `rest` and the separator start with the separator.
+        let splits = |rest: &str| if rest.len() >= separator.len() {
+            rest.starts_with(separator)
+        } else {
+            separator.starts_with(rest) && separator.starts_with(&separator[rest.len()..])
+        };
+
+        // Sorted, the words can be looked up by halves.
+        let is_word = |start: &str| self.words.binary_search_by(|word| word.as_str().cmp(start)).is_ok();
+
+        self.words.iter().find_map(|longer| {
+            let index = longer
+                .char_indices()
+                .skip(1)
+                .map(|(index, _)| index)
+                .find(|index| splits(&longer[*index..]) && is_word(&longer[..*index]))?;
+
+            Some((&longer[..index], longer.as_str()))
+        })
+    }
+}
+
+// Leaves out the words themselves, which would fill a log.
+impl fmt::Debug for Wordlist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wordlist")
+            .field("fingerprint", &self.fingerprint)
+            .field("len", &self.words.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Tests that a loaded list is cleaned up and named after its words.
+    #[test]
+    fn parse_cleans_up() {
+
+        let wordlist = Wordlist::parse("\u{feff}pear\n\n11 Apple\n  fig  \npear\napple\n").unwrap();
+
+        assert_eq!(wordlist.words(), ["apple", "fig", "pear"]);
+        assert_eq!(wordlist.fingerprint().len(), 16);
+        assert_eq!(wordlist.bits_per_word(), 3f64.log2());
+
+        // Only the set of words matters.
+        assert_eq!(Wordlist::parse("fig\npear\napple").unwrap(), wordlist);
+        assert_ne!(Wordlist::parse("fig\npear\napples").unwrap().fingerprint(), wordlist.fingerprint());
+    }
+
+    // Tests that the bundled list is the whole EFF large wordlist.
+    #[test]
+    fn eff_large_is_complete() {
+
+        let wordlist = Wordlist::eff_large();
+
+        assert_eq!(wordlist.len(), 7776);
+        assert_eq!(wordlist.fingerprint(), "55e72319c9479de4");
+        assert_eq!(wordlist.words().first().map(String::as_str), Some("abacus"));
+        assert_eq!(wordlist.words().last().map(String::as_str), Some("zoom"));
+    }
+
+    // Tests that lists that can't make sound passphrases are refused.
+    #[test]
+    fn parse_rejects_bad_lists() {
+        for text in ["", "apple\napple", "Apple\napple", "apple pie\nfig", "1 2 3\nfig"] {
+            assert!(matches!(Wordlist::parse(text), Err(PassgenError::InvalidPassphrase(_))), "{:?}", text);
+        }
+
+        assert!(Wordlist::load("/nonexistent/words.txt").is_err());
+    }
+
+    // Tests that a word that starts another is found.
+    #[test]
+    fn prefix_pair_finds_prefixes() {
+        assert_eq!(Wordlist::parse("apple\nfig\napplesauce").unwrap().prefix_pair(""), Some(("apple", "applesauce")));
+        assert_eq!(Wordlist::parse("apple\nfig\npear").unwrap().prefix_pair(""), None);
+
+        // With a separator, only a word followed by it counts.
+        assert_eq!(Wordlist::parse("apple\nfig\napplesauce").unwrap().prefix_pair("-"), None);
+        assert_eq!(Wordlist::parse("yo\nyo-yo").unwrap().prefix_pair("-"), Some(("yo", "yo-yo")));
+        assert_eq!(Wordlist::parse("yo-\nyo").unwrap().prefix_pair("--"), Some(("yo", "yo-")));
+        assert_eq!(Wordlist::eff_large().prefix_pair("-"), None);
+    }
+}